@@ -0,0 +1,297 @@
+// Binary space partitioning of static geometry
+//
+// Each node keeps the polygons lying in its plane. Those on the side its normal
+// points to go in the front subtree, the others in the back one, and polygons
+// crossing the plane are cut in two. Going through the far side of every plane
+// before the near one then reaches each polygon after everything it could be
+// hiding, from any point of view: the painter's algorithm without any sorting
+
+use crate::{clip_polygon_with, Material, Mesh, Plane, Point3D};
+
+// How far from a plane a vertex still counts as being on it
+const PLANE_EPSILON: f32 = 1e-3;
+
+// Candidates looked at for the plane of each node
+const SPLITTER_CANDIDATES: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct BspPolygon {
+    pub vertices: Vec<Point3D>,
+    // Computed like `Mesh::face_normal`, normalized
+    pub normal: Point3D,
+    // One per vertex, for smooth shading
+    pub normals: Vec<Point3D>,
+    // One per vertex too, or none for untextured faces
+    pub uvs: Vec<(f32, f32)>,
+    pub material: Material,
+    // Face it comes from, counting the faces of all the meshes in order
+    pub id: usize,
+}
+
+impl BspPolygon {
+    // None for degenerate polygons, which have no plane
+    pub fn new(vertices: Vec<Point3D>, material: Material) -> Option<BspPolygon> {
+        if vertices.len() < 3 {
+            return None;
+        }
+
+        let normal = (&vertices[1] - &vertices[0]) * (&vertices[vertices.len() - 1] - &vertices[0]);
+        if normal.norm() < f32::EPSILON {
+            return None;
+        }
+
+        let normal = normal.normalize();
+        Some(BspPolygon {
+            normals: vec![normal.clone(); vertices.len()],
+            uvs: vec![],
+            vertices,
+            normal,
+            material,
+            id: 0,
+        })
+    }
+
+    // Vertex normals other than the face's own, one per vertex
+    pub fn with_normals(mut self, normals: Vec<Point3D>) -> BspPolygon {
+        assert_eq!(normals.len(), self.vertices.len());
+        self.normals = normals;
+        self
+    }
+
+    // Texture coordinates, one per vertex
+    pub fn with_uvs(mut self, uvs: Vec<(f32, f32)>) -> BspPolygon {
+        assert_eq!(uvs.len(), self.vertices.len());
+        self.uvs = uvs;
+        self
+    }
+
+    fn plane(&self) -> Plane {
+        Plane::from_point_normal(&self.vertices[0], &self.normal)
+    }
+}
+
+// Parent of a node to come, and whether it is the front child
+type Link = Option<(usize, bool)>;
+
+#[derive(Debug, Clone)]
+struct BspNode {
+    plane: Plane,
+    polygons: Vec<BspPolygon>,
+    front: Option<usize>,
+    back: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BspTree {
+    nodes: Vec<BspNode>,
+}
+
+enum Side {
+    Front,
+    Back,
+    On,
+    Both,
+}
+
+impl BspTree {
+    // Faces are taken as they are, so the meshes should already be in world space
+    pub fn new(meshes: &[Mesh]) -> BspTree {
+        let mut polygons = vec![];
+        let mut id = 0;
+
+        for mesh in meshes.iter() {
+            let normals = mesh.vertex_normals();
+            for face in mesh.faces.iter() {
+                let polygon = BspPolygon::new(mesh.face_vertices(face), face.material.clone());
+                if let Some(polygon) = polygon {
+                    let mut polygon = polygon
+                        .with_normals(face.indices.iter().map(|i| normals[*i].clone()).collect());
+                    if mesh.has_uvs() {
+                        polygon =
+                            polygon.with_uvs(face.indices.iter().map(|i| mesh.uvs[*i]).collect());
+                    }
+                    polygon.id = id;
+                    polygons.push(polygon);
+                }
+                id += 1;
+            }
+        }
+
+        BspTree::from_polygons(polygons)
+    }
+
+    pub fn from_polygons(polygons: Vec<BspPolygon>) -> BspTree {
+        let mut tree = BspTree::default();
+
+        // Polygons still to place, and where to link the node made from them.
+        // No recursion, as unlucky levels can give deep trees
+        let mut pending: Vec<(Vec<BspPolygon>, Link)> = vec![(polygons, None)];
+
+        while let Some((polygons, parent)) = pending.pop() {
+            if polygons.is_empty() {
+                continue;
+            }
+
+            let plane = polygons[choose_splitter(&polygons)].plane();
+            let mut node = BspNode {
+                plane,
+                polygons: vec![],
+                front: None,
+                back: None,
+            };
+            let (mut front, mut back) = (vec![], vec![]);
+
+            for polygon in polygons {
+                match classify(&polygon, &node.plane) {
+                    Side::On => node.polygons.push(polygon),
+                    Side::Front => front.push(polygon),
+                    Side::Back => back.push(polygon),
+                    Side::Both => {
+                        let (f, b) = split(&polygon, &node.plane);
+                        front.extend(f);
+                        back.extend(b);
+                    }
+                }
+            }
+
+            let id = tree.nodes.len();
+            tree.nodes.push(node);
+            match parent {
+                Some((p, true)) => tree.nodes[p].front = Some(id),
+                Some((p, false)) => tree.nodes[p].back = Some(id),
+                None => {}
+            }
+
+            pending.push((front, Some((id, true))));
+            pending.push((back, Some((id, false))));
+        }
+
+        tree
+    }
+
+    // Number of polygons after splitting
+    pub fn len(&self) -> usize {
+        self.nodes.iter().map(|n| n.polygons.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Farthest first: each polygon comes after all those it may be behind
+    pub fn back_to_front(&self, eye: &Point3D) -> Vec<&BspPolygon> {
+        self.traverse(eye, true)
+    }
+
+    // Closest first: each polygon comes before all those it may hide
+    pub fn front_to_back(&self, eye: &Point3D) -> Vec<&BspPolygon> {
+        self.traverse(eye, false)
+    }
+
+    fn traverse(&self, eye: &Point3D, far_first: bool) -> Vec<&BspPolygon> {
+        enum Visit {
+            Node(usize),
+            Polygons(usize),
+        }
+
+        let mut ordered = vec![];
+        if self.nodes.is_empty() {
+            return ordered;
+        }
+
+        let mut stack = vec![Visit::Node(0)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Polygons(i) => ordered.extend(self.nodes[i].polygons.iter()),
+                Visit::Node(i) => {
+                    let node = &self.nodes[i];
+                    let (near, far) = if node.plane.distance(eye) >= 0. {
+                        (node.front, node.back)
+                    } else {
+                        (node.back, node.front)
+                    };
+                    let (first, last) = if far_first { (far, near) } else { (near, far) };
+
+                    // Pushed in reverse, to come out in order
+                    if let Some(last) = last {
+                        stack.push(Visit::Node(last));
+                    }
+                    stack.push(Visit::Polygons(i));
+                    if let Some(first) = first {
+                        stack.push(Visit::Node(first));
+                    }
+                }
+            }
+        }
+
+        ordered
+    }
+}
+
+fn classify(polygon: &BspPolygon, plane: &Plane) -> Side {
+    let (mut front, mut back) = (false, false);
+    for v in polygon.vertices.iter() {
+        let d = plane.distance(v);
+        front |= d > PLANE_EPSILON;
+        back |= d < -PLANE_EPSILON;
+    }
+
+    match (front, back) {
+        (true, true) => Side::Both,
+        (true, false) => Side::Front,
+        (false, true) => Side::Back,
+        (false, false) => Side::On,
+    }
+}
+
+fn split(polygon: &BspPolygon, plane: &Plane) -> (Option<BspPolygon>, Option<BspPolygon>) {
+    let behind = Plane::new(&plane.normal * -1., -plane.d);
+    let part = |plane: &Plane| {
+        let attributes: Vec<(Point3D, (f32, f32))> = polygon
+            .normals
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.clone(), polygon.uvs.get(i).copied().unwrap_or((0., 0.))))
+            .collect();
+        let (vertices, attributes) = clip_polygon_with(&polygon.vertices, &attributes, plane);
+        if vertices.is_empty() {
+            return None;
+        }
+        let (normals, uvs) = attributes.into_iter().unzip();
+        // Same plane as the whole, which a tiny piece may not give back
+        Some(BspPolygon {
+            vertices,
+            normal: polygon.normal.clone(),
+            normals,
+            uvs: if polygon.uvs.is_empty() { vec![] } else { uvs },
+            material: polygon.material.clone(),
+            id: polygon.id,
+        })
+    };
+
+    (part(plane), part(&behind))
+}
+
+// Among a few polygons spread over the list, the plane cutting the fewest
+// others, then leaving both sides closest in size
+fn choose_splitter(polygons: &[BspPolygon]) -> usize {
+    let step = (polygons.len() / SPLITTER_CANDIDATES).max(1);
+
+    (0..polygons.len())
+        .step_by(step)
+        .take(SPLITTER_CANDIDATES)
+        .min_by_key(|i| {
+            let plane = polygons[*i].plane();
+            let (mut front, mut back, mut splits) = (0i64, 0i64, 0i64);
+            for polygon in polygons.iter() {
+                match classify(polygon, &plane) {
+                    Side::Front => front += 1,
+                    Side::Back => back += 1,
+                    Side::Both => splits += 1,
+                    Side::On => {}
+                }
+            }
+            splits * 8 + (front - back).abs()
+        })
+        .unwrap_or(0)
+}
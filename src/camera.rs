@@ -0,0 +1,267 @@
+use crate::{
+    clip_polygon, clip_polygon_with, clip_segment, Interpolate, Mat4, Plane, Point3D, Quaternion,
+};
+
+// Projected vertex: pixel coordinates plus the distance along the view axis
+#[derive(Debug, Clone)]
+pub struct ScreenPoint {
+    pub x: f32,
+    pub y: f32,
+    pub depth: f32,
+}
+
+impl ScreenPoint {
+    pub fn new(x: f32, y: f32, depth: f32) -> ScreenPoint {
+        ScreenPoint { x, y, depth }
+    }
+}
+
+impl From<&ScreenPoint> for sdl2::rect::Point {
+    fn from(value: &ScreenPoint) -> sdl2::rect::Point {
+        sdl2::rect::Point::new(value.x.round() as i32, value.y.round() as i32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    position: Point3D,
+    // Camera to world rotation
+    orientation: Quaternion,
+    // World to camera, rebuilt whenever the position or orientation changes
+    view: Mat4,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Camera {
+    pub const DEFAULT_NEAR: f32 = 1.;
+    pub const DEFAULT_FAR: f32 = 10000.;
+    // Slack for vertices that were just clipped onto the near or far plane
+    const CLIP_EPSILON: f32 = 1e-3;
+
+    pub fn new(
+        position: Point3D,
+        orientation: Quaternion,
+        fov: f32,
+        width: u32,
+        height: u32,
+    ) -> Camera {
+        let mut camera = Camera {
+            position,
+            orientation,
+            view: Mat4::IDENTITY,
+            fov,
+            near: Self::DEFAULT_NEAR,
+            far: Self::DEFAULT_FAR,
+            width,
+            height,
+        };
+        camera.update_view();
+
+        camera
+    }
+
+    pub fn position(&self) -> &Point3D {
+        &self.position
+    }
+
+    pub fn orientation(&self) -> &Quaternion {
+        &self.orientation
+    }
+
+    pub fn set_position(&mut self, position: Point3D) {
+        self.position = position;
+        self.update_view();
+    }
+
+    pub fn set_orientation(&mut self, orientation: Quaternion) {
+        self.orientation = orientation.normalize();
+        self.update_view();
+    }
+
+    pub fn translate(&mut self, delta: &Point3D) {
+        self.position += delta.clone();
+        self.update_view();
+    }
+
+    // Turn around the world's vertical axis
+    pub fn yaw(&mut self, angle: f32) {
+        let q = Quaternion::from_axis_angle(&Point3D::Y, angle);
+        self.set_orientation(q * self.orientation.clone());
+    }
+
+    // Tilt around the camera's own horizontal axis
+    pub fn pitch(&mut self, angle: f32) {
+        let q = Quaternion::from_axis_angle(&Point3D::X, angle);
+        self.set_orientation(self.orientation.clone() * q);
+    }
+
+    pub fn forward(&self) -> Point3D {
+        Point3D::Z.rotate_by(&self.orientation)
+    }
+
+    pub fn right(&self) -> Point3D {
+        Point3D::X.rotate_by(&self.orientation)
+    }
+
+    pub fn down(&self) -> Point3D {
+        Point3D::Y.rotate_by(&self.orientation)
+    }
+
+    pub fn view_matrix(&self) -> &Mat4 {
+        &self.view
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        Mat4::perspective(
+            self.fov,
+            self.width as f32 / self.height as f32,
+            self.near,
+            self.far,
+        )
+    }
+
+    // Distance in pixels between the eye and the screen plane
+    pub fn focal_length(&self) -> f32 {
+        self.width as f32 / (2.0 * (self.fov / 2.0).tan())
+    }
+
+    pub fn near_plane(&self) -> Plane {
+        let forward = self.forward();
+        let point = &self.position + &(&forward * self.near);
+        Plane::from_point_normal(&point, &forward)
+    }
+
+    pub fn far_plane(&self) -> Plane {
+        let forward = self.forward();
+        let point = &self.position + &(&forward * self.far);
+        Plane::from_point_normal(&point, &(&Point3D::ZERO - &forward))
+    }
+
+    // Cut a world space polygon to the part lying between the near and far planes
+    pub fn clip(&self, vertices: &[Point3D]) -> Vec<Point3D> {
+        let clipped = clip_polygon(vertices, &self.near_plane());
+        clip_polygon(&clipped, &self.far_plane())
+    }
+
+    // Same as `clip`, with attributes following the corners
+    pub fn clip_with<T: Interpolate>(
+        &self,
+        vertices: &[Point3D],
+        attributes: &[T],
+    ) -> (Vec<Point3D>, Vec<T>) {
+        let (clipped, attributes) = clip_polygon_with(vertices, attributes, &self.near_plane());
+        clip_polygon_with(&clipped, &attributes, &self.far_plane())
+    }
+
+    // Same as `clip`, for a line
+    pub fn clip_segment(&self, a: &Point3D, b: &Point3D) -> Option<(Point3D, Point3D)> {
+        let (a, b) = clip_segment(a, b, &self.near_plane())?;
+        clip_segment(&a, &b, &self.far_plane())
+    }
+
+    pub fn to_view(&self, point: &Point3D) -> Point3D {
+        self.view.transform_point(point)
+    }
+
+    pub fn project(&self, point: &Point3D) -> Option<ScreenPoint> {
+        self.project_view(&self.to_view(point))
+    }
+
+    // Same as `project` for a point already in camera space
+    pub fn project_view(&self, value: &Point3D) -> Option<ScreenPoint> {
+        if value.z < self.near - Self::CLIP_EPSILON || value.z > self.far + Self::CLIP_EPSILON {
+            return None;
+        }
+
+        let d = self.focal_length();
+
+        let screen_x = (value.x / value.z) * d + self.width as f32 / 2.0;
+        let screen_y = (value.y / value.z) * d + self.height as f32 / 2.0;
+
+        Some(ScreenPoint::new(screen_x, screen_y, value.z))
+    }
+
+    fn update_view(&mut self) {
+        self.view = Mat4::rotation(&self.orientation.inverse())
+            * Mat4::translation(&(&Point3D::ZERO - &self.position));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 200;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Point3D::ZERO,
+            Quaternion::IDENTITY,
+            90f32.to_radians(),
+            WIDTH,
+            HEIGHT,
+        )
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn view_axis_projects_to_the_centre() {
+        let p = camera().project(&Point3D::new(0., 0., 50.)).unwrap();
+        assert!(close(p.x, WIDTH as f32 / 2.) && close(p.y, HEIGHT as f32 / 2.));
+        assert!(close(p.depth, 50.));
+    }
+
+    #[test]
+    fn points_outside_near_and_far_are_dropped() {
+        let camera = camera();
+        assert!(camera
+            .project(&Point3D::new(0., 0., camera.near / 2.))
+            .is_none());
+        assert!(camera.project(&Point3D::new(0., 0., -10.)).is_none());
+        assert!(camera
+            .project(&Point3D::new(0., 0., camera.far * 2.))
+            .is_none());
+        assert!(camera.project(&Point3D::new(0., 0., camera.near)).is_some());
+        assert!(camera.project(&Point3D::new(0., 0., camera.far)).is_some());
+    }
+
+    #[test]
+    fn horizontal_fov_edges_land_on_the_screen_edges() {
+        let camera = camera();
+        // tan(45°) = 1: the edges of a 90° field of view are at x = ±z
+        let left = camera.project(&Point3D::new(-30., 0., 30.)).unwrap();
+        let right = camera.project(&Point3D::new(30., 0., 30.)).unwrap();
+        assert!(close(left.x, 0.));
+        assert!(close(right.x, WIDTH as f32));
+    }
+
+    #[test]
+    fn positive_yaw_turns_right() {
+        let mut camera = camera();
+        camera.yaw(20f32.to_radians());
+        // What was ahead moves left, what was on the right comes ahead
+        let ahead = camera.project(&Point3D::new(0., 0., 50.)).unwrap();
+        assert!(ahead.x < WIDTH as f32 / 2.);
+        assert!(close(ahead.y, HEIGHT as f32 / 2.));
+        assert!(camera.forward().x > 0.);
+    }
+
+    #[test]
+    fn positive_pitch_looks_up() {
+        let mut camera = camera();
+        camera.pitch(20f32.to_radians());
+        // Up is -Y: what was ahead moves down the screen
+        let ahead = camera.project(&Point3D::new(0., 0., 50.)).unwrap();
+        assert!(ahead.y > HEIGHT as f32 / 2.);
+        assert!(close(ahead.x, WIDTH as f32 / 2.));
+        assert!(camera.forward().y < 0.);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::Point3D;
+
+// Points p such that `normal . p = d`; the normal points towards the side that is kept
+#[derive(Debug, Clone)]
+pub struct Plane {
+    pub normal: Point3D,
+    pub d: f32,
+}
+
+impl Plane {
+    pub fn new(normal: Point3D, d: f32) -> Plane {
+        Plane { normal, d }
+    }
+
+    pub fn from_point_normal(point: &Point3D, normal: &Point3D) -> Plane {
+        let normal = normal.normalize();
+        let d = normal.dot(point);
+        Plane { normal, d }
+    }
+
+    // Signed distance, positive on the kept side
+    pub fn distance(&self, point: &Point3D) -> f32 {
+        self.normal.dot(point) - self.d
+    }
+}
+
+// Values carried by the corners of a polygon, blended where clipping or
+// rasterizing needs them between corners
+pub trait Interpolate: Clone {
+    // `self` at t = 0, `other` at t = 1
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+
+    // Weights adding up to 1, like barycentric coordinates
+    fn blend(values: [&Self; 3], weights: [f32; 3]) -> Self;
+}
+
+impl Interpolate for () {
+    fn lerp(&self, _: &(), _: f32) {}
+
+    fn blend(_: [&(); 3], _: [f32; 3]) {}
+}
+
+impl Interpolate for f32 {
+    fn lerp(&self, other: &f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+
+    fn blend(values: [&f32; 3], weights: [f32; 3]) -> f32 {
+        values[0] * weights[0] + values[1] * weights[1] + values[2] * weights[2]
+    }
+}
+
+impl Interpolate for Point3D {
+    fn lerp(&self, other: &Point3D, t: f32) -> Point3D {
+        self + &(&(other - self) * t)
+    }
+
+    fn blend(values: [&Point3D; 3], weights: [f32; 3]) -> Point3D {
+        let [a, b, c] = values;
+        &(&(a * weights[0]) + &(b * weights[1])) + &(c * weights[2])
+    }
+}
+
+impl<const N: usize> Interpolate for [f32; N] {
+    fn lerp(&self, other: &[f32; N], t: f32) -> [f32; N] {
+        std::array::from_fn(|i| self[i] + (other[i] - self[i]) * t)
+    }
+
+    fn blend(values: [&[f32; N]; 3], weights: [f32; 3]) -> [f32; N] {
+        let [a, b, c] = values;
+        std::array::from_fn(|i| a[i] * weights[0] + b[i] * weights[1] + c[i] * weights[2])
+    }
+}
+
+impl<A: Interpolate, B: Interpolate> Interpolate for (A, B) {
+    fn lerp(&self, other: &(A, B), t: f32) -> (A, B) {
+        (self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+    }
+
+    fn blend(values: [&(A, B); 3], weights: [f32; 3]) -> (A, B) {
+        let [a, b, c] = values;
+        (
+            A::blend([&a.0, &b.0, &c.0], weights),
+            B::blend([&a.1, &b.1, &c.1], weights),
+        )
+    }
+}
+
+// Sutherland-Hodgman against a single plane: the result can have more or fewer
+// vertices than the input, and is empty when the polygon is entirely clipped
+pub fn clip_polygon(vertices: &[Point3D], plane: &Plane) -> Vec<Point3D> {
+    clip_polygon_with(vertices, &vec![(); vertices.len()], plane).0
+}
+
+// Part of the segment on the front side of `plane`, if any
+pub fn clip_segment(a: &Point3D, b: &Point3D, plane: &Plane) -> Option<(Point3D, Point3D)> {
+    let (d_a, d_b) = (plane.distance(a), plane.distance(b));
+
+    match (d_a >= 0., d_b >= 0.) {
+        (true, true) => Some((a.clone(), b.clone())),
+        (false, false) => None,
+        (true, false) => Some((a.clone(), a.lerp(b, d_a / (d_a - d_b)))),
+        (false, true) => Some((a.lerp(b, d_a / (d_a - d_b)), b.clone())),
+    }
+}
+
+// Same as `clip_polygon`, also giving the attributes of the new corners
+pub fn clip_polygon_with<T: Interpolate>(
+    vertices: &[Point3D],
+    attributes: &[T],
+    plane: &Plane,
+) -> (Vec<Point3D>, Vec<T>) {
+    let mut clipped: Vec<Point3D> = vec![];
+    let mut clipped_attributes: Vec<T> = vec![];
+
+    for (i, current) in vertices.iter().enumerate() {
+        let j = (i + 1) % vertices.len();
+        let next = &vertices[j];
+
+        let d_current = plane.distance(current);
+        let d_next = plane.distance(next);
+
+        if d_current >= 0. {
+            clipped.push(current.clone());
+            clipped_attributes.push(attributes[i].clone());
+        }
+
+        if (d_current >= 0.) != (d_next >= 0.) {
+            let t = d_current / (d_current - d_next);
+            clipped.push(current.lerp(next, t));
+            clipped_attributes.push(attributes[i].lerp(&attributes[j], t));
+        }
+    }
+
+    if clipped.len() < 3 {
+        clipped.clear();
+        clipped_attributes.clear();
+    }
+
+    (clipped, clipped_attributes)
+}
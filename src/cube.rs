@@ -11,96 +11,230 @@
 
 use sdl2::pixels::Color;
 
-use crate::{Point3D, Square};
+use crate::{Mesh, Point3D, Quaternion, Square};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    Back,
+    Front,
+}
 
-use std::convert::Into;
+impl CubeFace {
+    // Same order as `Cube::FACES`
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::Top,
+        CubeFace::Bottom,
+        CubeFace::Left,
+        CubeFace::Right,
+        CubeFace::Back,
+        CubeFace::Front,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
 
+#[derive(Debug, Clone)]
 pub struct Cube {
     vertices: [Point3D; 8],
-    color: Color,
+    colors: [Color; 6],
 }
 
 impl Cube {
+    // Corners of each face, ordered for the normal to point outwards, in
+    // `CubeFace` order
+    pub(crate) const FACES: [[usize; 4]; 6] = [
+        [0, 1, 3, 2],
+        [6, 7, 5, 4],
+        [6, 4, 0, 2],
+        [1, 5, 7, 3],
+        [2, 3, 7, 6],
+        [0, 4, 5, 1],
+    ];
+
+    // Texture coordinates of the corners of `FACES`. The top has the far side
+    // up, the bottom the near side
+    const FACE_UVS: [[(f32, f32); 4]; 6] = [
+        [(0., 1.), (1., 1.), (1., 0.), (0., 0.)],
+        [(0., 1.), (1., 1.), (1., 0.), (0., 0.)],
+        [(0., 1.), (1., 1.), (1., 0.), (0., 0.)],
+        [(0., 0.), (0., 1.), (1., 1.), (1., 0.)],
+        [(1., 0.), (0., 0.), (0., 1.), (1., 1.)],
+        [(0., 0.), (0., 1.), (1., 1.), (1., 0.)],
+    ];
+
     pub fn new(corner: &Point3D, color: Color, edge_size: f32) -> Cube {
+        Cube::cuboid(
+            corner,
+            &Point3D::new(edge_size, edge_size, edge_size),
+            color,
+        )
+    }
+
+    // Box with its own size along each axis. A negative size extends the box
+    // the other way from `corner` rather than turning it inside out
+    pub fn cuboid(corner: &Point3D, size: &Point3D, color: Color) -> Cube {
         let mut vertices = [Point3D::ZERO; 8];
-	
-        let x = &Point3D::X * edge_size;
-        let y = &Point3D::Y * edge_size;
-        let z = &Point3D::Z * edge_size;
+
+        let corner = Point3D::new(
+            corner.x + size.x.min(0.),
+            corner.y + size.y.min(0.),
+            corner.z + size.z.min(0.),
+        );
+
+        let x = &Point3D::X * size.x.abs();
+        let y = &Point3D::Y * size.y.abs();
+        let z = &Point3D::Z * size.z.abs();
 
         vertices[0] = corner.clone();
-        vertices[1] = corner + &x;
-        vertices[2] = corner + &z;
-        vertices[3] = corner + &x + z;
+        vertices[1] = &corner + &x;
+        vertices[2] = &corner + &z;
+        vertices[3] = &corner + &x + z;
         vertices[4] = &vertices[0] + &y;
         vertices[5] = &vertices[1] + &y;
         vertices[6] = &vertices[2] + &y;
         vertices[7] = &vertices[3] + &y;
 
-        Cube { vertices, color }
+        Cube {
+            vertices,
+            colors: [color; 6],
+        }
+    }
+
+    pub fn center(&self) -> Point3D {
+        &(&self.vertices[0] + &self.vertices[7]) * 0.5
+    }
+
+    // Turned around `pivot`. Rotations keep the corners' order, so the face
+    // normals stay outwards
+    pub fn rotated(&self, rotation: &Quaternion, pivot: &Point3D) -> Cube {
+        Cube {
+            vertices: self
+                .vertices
+                .clone()
+                .map(|v| pivot + &(&v - pivot).rotate_by(rotation)),
+            colors: self.colors,
+        }
+    }
+
+    pub fn rotated_around_center(&self, rotation: &Quaternion) -> Cube {
+        self.rotated(rotation, &self.center())
+    }
+
+    pub fn vertices(&self) -> &[Point3D; 8] {
+        &self.vertices
+    }
+
+    pub fn face_color(&self, face: CubeFace) -> Color {
+        self.colors[face.index()]
+    }
+
+    pub fn set_face_color(&mut self, face: CubeFace, color: Color) {
+        self.colors[face.index()] = color;
+    }
+
+    pub fn with_face_color(mut self, face: CubeFace, color: Color) -> Cube {
+        self.set_face_color(face, color);
+        self
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.colors = [color; 6];
+    }
+
+    // A different colour on each side, to check which face is which and that
+    // they all face outwards
+    pub fn debug_colors(self) -> Cube {
+        self.with_face_color(CubeFace::Top, Color::RED)
+            .with_face_color(CubeFace::Bottom, Color::CYAN)
+            .with_face_color(CubeFace::Left, Color::GREEN)
+            .with_face_color(CubeFace::Right, Color::MAGENTA)
+            .with_face_color(CubeFace::Back, Color::BLUE)
+            .with_face_color(CubeFace::Front, Color::YELLOW)
+    }
+}
+
+// Each face gets its own corners, for the whole texture to show on every face,
+// upright on the sides
+impl From<Cube> for Mesh {
+    fn from(value: Cube) -> Mesh {
+        let mut mesh = Mesh::new();
+
+        for ((corners, uvs), color) in Cube::FACES.iter().zip(Cube::FACE_UVS).zip(value.colors) {
+            let indices: Vec<usize> = corners
+                .iter()
+                .map(|i| mesh.add_vertex(value.vertices[*i].clone()))
+                .collect();
+            mesh.uvs.extend(uvs);
+            mesh.add_face(&indices, color);
+        }
+
+        mesh
     }
 }
 
-impl Into<Vec<Square>> for Cube {
-    fn into(self) -> Vec<Square> {
-        let mut faces: Vec<Square> = vec![];
-
-        let top = [
-            self.vertices[0].clone(),
-            self.vertices[1].clone(),
-            self.vertices[3].clone(),
-            self.vertices[2].clone(),
-        ];
-        let top = top.as_slice();
-
-        let back = [
-            self.vertices[2].clone(),
-            self.vertices[3].clone(),
-            self.vertices[7].clone(),
-            self.vertices[6].clone(),
-        ];
-        let back = back.as_slice();
-
-        let front = [
-            self.vertices[0].clone(),
-            self.vertices[4].clone(),
-            self.vertices[5].clone(),
-            self.vertices[1].clone(),
-        ];
-        let front = front.as_slice();
-
-        let left = [
-            self.vertices[6].clone(),
-            self.vertices[4].clone(),
-            self.vertices[0].clone(),
-            self.vertices[2].clone(),
-        ];
-        let left = left.as_slice();
-
-        let right = [
-            self.vertices[1].clone(),
-            self.vertices[5].clone(),
-            self.vertices[7].clone(),
-            self.vertices[3].clone(),
-        ];
-        let right = right.as_slice();
-
-        let bottom = [
-            self.vertices[6].clone(),
-            self.vertices[7].clone(),
-            self.vertices[5].clone(),
-            self.vertices[4].clone(),
-        ];
-        let bottom = bottom.as_slice();
-
-        // TODO: ORDER MATTERS ! ORDER DYNAMICALLY CONSIDERING THE DISTANCE TO THE PLAYER
-        faces.push(Square::new(top, &self.color));
-        faces.push(Square::new(bottom, &self.color));
-        faces.push(Square::new(left, &self.color));
-        faces.push(Square::new(right, &self.color));
-        faces.push(Square::new(back, &self.color));
-        faces.push(Square::new(front, &self.color));
-
-        faces
+impl From<Cube> for Vec<Square> {
+    fn from(value: Cube) -> Vec<Square> {
+        Cube::FACES
+            .iter()
+            .zip(value.colors.iter())
+            .map(|(indices, color)| {
+                let vertices = indices.map(|i| value.vertices[i].clone());
+                Square::new(&vertices, color)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Turned so that no face is aligned with an axis
+    fn cubes() -> Vec<Cube> {
+        let cube = Cube::cuboid(
+            &Point3D::new(-10., 5., 30.),
+            &Point3D::new(4., 6., 8.),
+            Color::WHITE,
+        );
+        let rotation = Quaternion::from_axis_angle(&Point3D::new(1., 2., 3.).normalize(), 0.7);
+        vec![cube.clone(), cube.rotated_around_center(&rotation)]
+    }
+
+    fn face_center(vertices: &[Point3D]) -> Point3D {
+        &vertices.iter().fold(Point3D::ZERO, |sum, v| &sum + v) * (1. / vertices.len() as f32)
+    }
+
+    #[test]
+    fn square_normals_point_outwards() {
+        for cube in cubes() {
+            let center = cube.center();
+            let squares: Vec<Square> = cube.into();
+            assert_eq!(squares.len(), 6);
+            for square in squares {
+                let outwards = &face_center(&square.vertices) - &center;
+                let normal = square.normal().normalize();
+                assert!(normal.dot(&outwards.normalize()) > 0.999);
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_face_normals_point_outwards() {
+        for cube in cubes() {
+            let center = cube.center();
+            let mesh = Mesh::from(cube);
+            assert_eq!(mesh.faces.len(), 6);
+            for face in mesh.faces.iter() {
+                let outwards = &face_center(&mesh.face_vertices(face)) - &center;
+                let normal = mesh.face_normal(face).normalize();
+                assert!(normal.dot(&outwards.normalize()) > 0.999);
+            }
+        }
     }
 }
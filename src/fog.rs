@@ -0,0 +1,99 @@
+// Effects of the distance to the camera
+//
+// Distances are depths along the view axis, like in the depth buffer. Each
+// effect is computed either once per face, at its centre, or at every pixel
+
+use sdl2::pixels::Color;
+
+use crate::color_mul;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+    // None up to `start`, growing evenly to full at `end`
+    Linear { start: f32, end: f32 },
+    // 1 - e^(-density * distance)
+    Exponential { density: f32 },
+    // 1 - e^(-(density * distance)²): clearer up close, thicker farther
+    ExponentialSquared { density: f32 },
+}
+
+// Faces fade into `color` with the distance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Color,
+    pub per_pixel: bool,
+}
+
+impl Fog {
+    pub fn new(mode: FogMode, color: Color) -> Fog {
+        Fog {
+            mode,
+            color,
+            per_pixel: false,
+        }
+    }
+
+    // How much of the fog colour shows at `distance`, from 0 to 1
+    pub fn amount(&self, distance: f32) -> f32 {
+        let amount = match self.mode {
+            FogMode::Linear { start, end } => {
+                if end > start {
+                    (distance - start) / (end - start)
+                } else if distance >= end {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            FogMode::Exponential { density } => 1. - (-density * distance).exp(),
+            FogMode::ExponentialSquared { density } => 1. - (-(density * distance).powi(2)).exp(),
+        };
+        amount.clamp(0., 1.)
+    }
+
+    pub fn apply(&self, color: Color, distance: f32) -> Color {
+        let t = self.amount(distance);
+        let channel = |c: u8, f: u8| (c as f32 + (f as f32 - c as f32) * t).round() as u8;
+        Color::RGBA(
+            channel(color.r, self.color.r),
+            channel(color.g, self.color.g),
+            channel(color.b, self.color.b),
+            color.a,
+        )
+    }
+}
+
+// Faces get darker with the distance, like with Doom's light diminishing:
+// full brightness up to `distance`, then falling with the inverse of the
+// distance, as the size of things on screen does, but never below `min`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightFalloff {
+    pub distance: f32,
+    pub min: f32,
+    pub per_pixel: bool,
+}
+
+impl LightFalloff {
+    pub const DEFAULT_MIN: f32 = 0.2;
+
+    pub fn new(distance: f32, min: f32) -> LightFalloff {
+        LightFalloff {
+            distance,
+            min,
+            per_pixel: false,
+        }
+    }
+
+    // Brightness left at `distance`, from `min` to 1
+    pub fn factor(&self, distance: f32) -> f32 {
+        if distance <= self.distance {
+            return 1.;
+        }
+        (self.distance / distance).max(self.min).min(1.)
+    }
+
+    pub fn apply(&self, color: Color, distance: f32) -> Color {
+        color_mul(&color, self.factor(distance))
+    }
+}
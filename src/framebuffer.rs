@@ -0,0 +1,245 @@
+use sdl2::pixels::Color;
+
+use crate::ScreenPoint;
+
+// How much farther than the depth buffer a line can be and still show, as a
+// fraction of the depth
+const LINE_DEPTH_BIAS: f32 = 5e-3;
+
+// Pixel being drawn by `fill_triangle`. The weights are the perspective correct
+// barycentric coordinates of the pixel centre, to interpolate vertex attributes
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    pub x: u32,
+    pub y: u32,
+    pub depth: f32,
+    pub weights: [f32; 3],
+    // How the weights change to the next pixel right and down, for the rate of
+    // change of the attributes across the screen
+    pub weights_dx: [f32; 3],
+    pub weights_dy: [f32; 3],
+}
+
+// RGBA colour buffer with a depth buffer holding the view distance of each pixel
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    depth: Vec<f32>,
+    depth_test: bool,
+}
+
+impl Framebuffer {
+    pub const BYTES_PER_PIXEL: usize = 4;
+
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        let size = width as usize * height as usize;
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0; size * Self::BYTES_PER_PIXEL],
+            depth: vec![f32::INFINITY; size],
+            depth_test: true,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Raw RGBA bytes, row after row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    // Number of bytes in a row
+    pub fn pitch(&self) -> usize {
+        self.width as usize * Self::BYTES_PER_PIXEL
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_exact_mut(Self::BYTES_PER_PIXEL) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        self.depth.fill(f32::INFINITY);
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        let i = self.index(x, y) * Self::BYTES_PER_PIXEL;
+        let p = &self.pixels[i..i + Self::BYTES_PER_PIXEL];
+        Color::RGBA(p[0], p[1], p[2], p[3])
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let i = self.index(x, y) * Self::BYTES_PER_PIXEL;
+        self.pixels[i..i + Self::BYTES_PER_PIXEL]
+            .copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+
+    // Without the test, the last pixel drawn wins: for callers drawing back to
+    // front. Depths are still written either way
+    pub fn set_depth_test(&mut self, enabled: bool) {
+        self.depth_test = enabled;
+    }
+
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    pub fn depth_at(&self, x: u32, y: u32) -> f32 {
+        self.depth[self.index(x, y)]
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    // Pixels the line goes through get `color`, where it passes the depth test.
+    // Lines lying on a face pass the test over that face, so that its edges
+    // can be drawn over it
+    pub fn draw_line(&mut self, a: &ScreenPoint, b: &ScreenPoint, color: Color) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+
+        // Liang-Barsky: only walk along the part on screen
+        let (mut start, mut end) = (0f32, 1f32);
+        for (p, q) in [
+            (-dx, a.x),
+            (dx, width - a.x),
+            (-dy, a.y),
+            (dy, height - a.y),
+        ] {
+            if p == 0. {
+                if q < 0. {
+                    return;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < 0. {
+                start = start.max(t);
+            } else {
+                end = end.min(t);
+            }
+        }
+        if start > end {
+            return;
+        }
+
+        let steps = (dx.abs().max(dy.abs()) * (end - start)).ceil().max(1.) as u32;
+        for step in 0..=steps {
+            let t = start + (end - start) * step as f32 / steps as f32;
+            let (x, y) = ((a.x + dx * t).floor(), (a.y + dy * t).floor());
+            if x < 0. || y < 0. || x >= width || y >= height {
+                continue;
+            }
+            let (x, y) = (x as u32, y as u32);
+
+            // 1/z is linear in screen space
+            let depth = 1. / (1. / a.depth + (1. / b.depth - 1. / a.depth) * t);
+            if self.depth_test && depth > self.depth[self.index(x, y)] * (1. + LINE_DEPTH_BIAS) {
+                continue;
+            }
+            self.set_pixel(x, y, color);
+        }
+    }
+
+    // Convex polygon, drawn as a fan of triangles
+    pub fn fill_polygon(&mut self, points: &[ScreenPoint], color: Color) {
+        for i in 1..points.len().saturating_sub(1) {
+            self.fill_triangle([&points[0], &points[i], &points[i + 1]], |_| color);
+        }
+    }
+
+    // Pixels whose centre is inside the triangle and closer than what is already
+    // there (unless the depth test is off) get the colour returned by `shade`.
+    // Both windings are accepted
+    pub fn fill_triangle<F>(&mut self, tri: [&ScreenPoint; 3], mut shade: F)
+    where
+        F: FnMut(&Fragment) -> Color,
+    {
+        let [a, b, c] = tri;
+
+        let area = edge(a, b, c.x, c.y);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.) as u32;
+        let max_x = a.x.max(b.x).max(c.x).ceil().min(self.width as f32) as u32;
+        let max_y = a.y.max(b.y).max(c.y).ceil().min(self.height as f32) as u32;
+
+        // 1/z is linear in screen space, z is not
+        let perspective = |l: [f32; 3]| {
+            let inv_z = l[0] / a.depth + l[1] / b.depth + l[2] / c.depth;
+            let depth = 1. / inv_z;
+            let weights = [
+                l[0] / a.depth * depth,
+                l[1] / b.depth * depth,
+                l[2] / c.depth * depth,
+            ];
+            (depth, weights)
+        };
+        let barycentric = |px: f32, py: f32| {
+            [
+                edge(b, c, px, py) / area,
+                edge(c, a, px, py) / area,
+                edge(a, b, px, py) / area,
+            ]
+        };
+        let difference = |next: [f32; 3], current: &[f32; 3]| {
+            [
+                next[0] - current[0],
+                next[1] - current[1],
+                next[2] - current[2],
+            ]
+        };
+
+        for y in min_y..max_y {
+            let py = y as f32 + 0.5;
+
+            for x in min_x..max_x {
+                let px = x as f32 + 0.5;
+
+                let l = barycentric(px, py);
+                if l[0] < 0. || l[1] < 0. || l[2] < 0. {
+                    continue;
+                }
+
+                let (depth, weights) = perspective(l);
+
+                let i = self.index(x, y);
+                if self.depth_test && depth >= self.depth[i] {
+                    continue;
+                }
+
+                // Outside of the triangle, the weights carry on as if it were bigger
+                let (_, right) = perspective(barycentric(px + 1., py));
+                let (_, below) = perspective(barycentric(px, py + 1.));
+
+                let fragment = Fragment {
+                    x,
+                    y,
+                    depth,
+                    weights_dx: difference(right, &weights),
+                    weights_dy: difference(below, &weights),
+                    weights,
+                };
+
+                let color = shade(&fragment);
+                self.depth[i] = depth;
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+// Twice the signed area of the triangle (a, b, p)
+fn edge(a: &ScreenPoint, b: &ScreenPoint, px: f32, py: f32) -> f32 {
+    (b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
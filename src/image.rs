@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::Framebuffer;
+
+// Binary PPM (P6), alpha is dropped
+pub fn write_ppm<W: Write>(framebuffer: &Framebuffer, out: &mut W) -> io::Result<()> {
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+
+    for pixel in framebuffer
+        .pixels()
+        .chunks_exact(Framebuffer::BYTES_PER_PIXEL)
+    {
+        out.write_all(&pixel[..3])?;
+    }
+
+    Ok(())
+}
+
+// 8 bit RGBA PNG. The image data is stored without compression so that no
+// deflate implementation is needed: files are big but valid
+pub fn write_png<W: Write>(framebuffer: &Framebuffer, out: &mut W) -> io::Result<()> {
+    out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+
+    let mut header = vec![];
+    header.extend_from_slice(&framebuffer.width().to_be_bytes());
+    header.extend_from_slice(&framebuffer.height().to_be_bytes());
+    // Bit depth, colour type (RGBA), compression, filter, interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_png_chunk(out, b"IHDR", &header)?;
+
+    // Every scanline starts with its filter type, 0 being "none"
+    let mut raw = Vec::with_capacity((framebuffer.pitch() + 1) * framebuffer.height() as usize);
+    for row in framebuffer.pixels().chunks_exact(framebuffer.pitch()) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_png_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+
+    write_png_chunk(out, b"IEND", &[])
+}
+
+// Format picked from the extension: `.ppm` or `.png`
+pub fn save_image(framebuffer: &Framebuffer, path: &Path) -> io::Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    let write: fn(&Framebuffer, &mut BufWriter<File>) -> io::Result<()> = match extension.as_deref()
+    {
+        Some("ppm") => write_ppm,
+        Some("png") => write_png,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported image format: {}", path.display()),
+            ))
+        }
+    };
+
+    let mut out = BufWriter::new(File::create(path)?);
+    write(framebuffer, &mut out)?;
+    out.flush()
+}
+
+fn write_png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&crc.finish().to_be_bytes())
+}
+
+// zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 65535;
+
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before b could overflow
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+
+    (b << 16) | a
+}
+
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    fn new() -> Crc32 {
+        let mut table = [0u32; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 {
+                    0xedb88320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+            }
+            *entry = c;
+        }
+
+        Crc32 {
+            table,
+            value: 0xffffffff,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.value =
+                self.table[((self.value ^ *byte as u32) & 0xff) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.value ^ 0xffffffff
+    }
+}
@@ -0,0 +1,693 @@
+// Level files
+//
+// Text, one entry per line. Lines starting with `#` are comments, and so is the
+// end of a line after a `#` on its own. Angles are in degrees and colours are
+// either names (red, grass...) or #rrggbb:
+//
+//   color <name> <colour>                         names a colour for the lines below
+//   spawn <x> <y> <z> [yaw <angle>] [pitch <angle>]
+//   cube <x> <y> <z> <size> [options]             corner and edge length
+//   cuboid <x> <y> <z> <sx> <sy> <sz> [options]   corner and size along each axis
+//   mesh <file> [options]                         .obj or .stl, relative to the level
+//   ambient <intensity>
+//   light directional <dx> <dy> <dz> [light options]
+//   light point <x> <y> <z> [light options]
+//   light spot <x> <y> <z> <dx> <dy> <dz> [angle <half-angle>] [light options]
+//   fog linear <start> <end> [fog options]
+//   fog exp <density> [fog options]
+//   fog exp2 <density> [fog options]
+//   falloff <distance> [min <brightness>] [per_pixel]
+//
+// Light options are `color <colour>`, `intensity <i>` and, except for
+// directional lights, `attenuation <constant> <linear> <quadratic>`. A level
+// without lights is drawn unlit; with lights, ambient light defaults to 0
+//
+// Linear fog starts at `start` and hides everything past `end`. Fog options
+// are `color <colour>` (black by default) and `per_pixel`, for the fog to be
+// computed at every pixel rather than once per face. `falloff` darkens what is
+// farther than `distance`, down to `min` (0.2 by default)
+//
+// Options of cubes and meshes are `name <name>`, `color <colour>`,
+// `rotate <x> <y> <z>`, `two_sided`, to see faces from behind too, and
+// `specular <strength>` and `shininess <exponent>` for highlights (none by
+// default). Cubes turn around their centre and can also take
+// `face <top|bottom|left|right|back|front> <colour>`. Meshes turn around their
+// origin, and are then moved with `at <x> <y> <z>` and `scale <sx> <sy> <sz>`
+//
+// Both can be textured with `texture <file>`, a .bmp or .ppm relative to the
+// level, tinted by their colour. The texture can be further set up with
+// `filter <nearest|bilinear|trilinear>`, `wrap <repeat|clamp>` and
+// `tile <u> <v>`, the number of times it repeats across each face of a cube,
+// or the factors of the texture coordinates of a mesh
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use sdl2::pixels::Color;
+
+use crate::{
+    load_stl, Attenuation, Cube, CubeFace, Filter, Fog, FogMode, Light, LightFalloff, LightKind,
+    Material, Mesh, ObjModel, Palette, Point3D, Quaternion, Rotation3, SceneGraph, StlError,
+    Texture, Transform, Wrap,
+};
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(PathBuf, io::Error),
+    Parse {
+        file: String,
+        line: usize,
+        entry: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            LevelError::Parse {
+                file,
+                line,
+                entry,
+                message,
+            } => write!(f, "{}:{}: {}: {}", file, line, entry, message),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LevelError::Io(_, e) => Some(e),
+            LevelError::Parse { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelObject {
+    pub name: String,
+    pub transform: Transform,
+    pub mesh: Mesh,
+}
+
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub objects: Vec<LevelObject>,
+    pub lights: Vec<Light>,
+    pub ambient: f32,
+    pub fog: Option<Fog>,
+    pub falloff: Option<LightFalloff>,
+    // Colours of the indexed rendering, like those of a WAD. None for the
+    // generated palette
+    pub palette: Option<Rc<Palette>>,
+    pub spawn: Point3D,
+    // In radians, for `Camera::yaw` and `Camera::pitch`
+    pub spawn_yaw: f32,
+    pub spawn_pitch: f32,
+}
+
+impl Default for Level {
+    fn default() -> Level {
+        Level {
+            objects: vec![],
+            lights: vec![],
+            ambient: 1.,
+            fog: None,
+            falloff: None,
+            palette: None,
+            spawn: Point3D::ZERO,
+            spawn_yaw: 0.,
+            spawn_pitch: 0.,
+        }
+    }
+}
+
+impl Level {
+    pub fn load(path: &Path) -> Result<Level, LevelError> {
+        let source = fs::read_to_string(path).map_err(|e| LevelError::Io(path.to_path_buf(), e))?;
+
+        LevelParser::new(&path.display().to_string(), path.parent()).parse(&source)
+    }
+
+    // Mesh files are looked up relatively to `base_dir`, or the working directory without one
+    pub fn parse(source: &str, base_dir: Option<&Path>) -> Result<Level, LevelError> {
+        LevelParser::new("<level>", base_dir).parse(source)
+    }
+
+    // Every object as a child of a "level" root node
+    pub fn scene(&self) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let root = scene.add_node("level", None, Transform::IDENTITY, None);
+
+        for object in self.objects.iter() {
+            scene.add_node(
+                &object.name,
+                Some(root),
+                object.transform.clone(),
+                Some(object.mesh.clone()),
+            );
+        }
+
+        scene.update_world_transforms();
+        scene
+    }
+}
+
+struct LevelParser<'a> {
+    file: String,
+    line: usize,
+    base_dir: Option<&'a Path>,
+    colors: HashMap<String, Color>,
+    // Line of the `spawn` entry, which may appear only once
+    spawn_line: Option<usize>,
+    ambient_set: bool,
+    // Loaded once even when used by many objects
+    textures: HashMap<(PathBuf, Filter, Wrap), Rc<Texture>>,
+    level: Level,
+}
+
+// `texture`, `filter`, `wrap` and `tile` options of an object
+#[derive(Default)]
+struct TextureOptions {
+    file: Option<String>,
+    filter: Filter,
+    wrap: Wrap,
+    tile: Option<(f32, f32)>,
+}
+
+// `specular` and `shininess` options of an object
+struct Highlights {
+    specular: f32,
+    shininess: f32,
+}
+
+impl Default for Highlights {
+    fn default() -> Highlights {
+        Highlights {
+            specular: Material::DEFAULT_SPECULAR,
+            shininess: Material::DEFAULT_SHININESS,
+        }
+    }
+}
+
+impl<'a> LevelParser<'a> {
+    fn new(file: &str, base_dir: Option<&'a Path>) -> LevelParser<'a> {
+        LevelParser {
+            file: file.to_string(),
+            line: 0,
+            base_dir,
+            colors: HashMap::new(),
+            spawn_line: None,
+            ambient_set: false,
+            textures: HashMap::new(),
+            level: Level::default(),
+        }
+    }
+
+    fn parse(mut self, source: &str) -> Result<Level, LevelError> {
+        for (i, line) in source.lines().enumerate() {
+            self.line = i + 1;
+
+            let tokens: Vec<&str> = line.split_whitespace().take_while(|t| *t != "#").collect();
+            let Some(keyword) = tokens.first().filter(|k| !k.starts_with('#')) else {
+                continue;
+            };
+
+            let mut entry = Entry::new(&tokens[1..]);
+            self.parse_entry(keyword, &mut entry)
+                .and_then(|_| entry.finish())
+                .map_err(|message| LevelError::Parse {
+                    file: self.file.clone(),
+                    line: self.line,
+                    entry: keyword.to_string(),
+                    message,
+                })?;
+        }
+
+        if !self.ambient_set && !self.level.lights.is_empty() {
+            self.level.ambient = 0.;
+        }
+
+        Ok(self.level)
+    }
+
+    fn parse_entry(&mut self, keyword: &str, entry: &mut Entry) -> Result<(), String> {
+        match keyword {
+            "color" => {
+                let name = entry.next("colour name")?;
+                if parse_color(name, &HashMap::new()).is_some() {
+                    return Err(format!("`{}` is already a colour", name));
+                }
+                let color = self.color(entry.next("colour")?)?;
+                self.colors.insert(name.to_string(), color);
+            }
+            "spawn" => {
+                if let Some(line) = self.spawn_line {
+                    return Err(format!("already set on line {}", line));
+                }
+                self.spawn_line = Some(self.line);
+
+                self.level.spawn = entry.point()?;
+                while let Some(option) = entry.option() {
+                    match option {
+                        "yaw" => self.level.spawn_yaw = entry.number()?.to_radians(),
+                        "pitch" => self.level.spawn_pitch = entry.number()?.to_radians(),
+                        _ => return Err(format!("unknown option `{}`", option)),
+                    }
+                }
+            }
+            "cube" | "cuboid" => {
+                let corner = entry.point()?;
+                let size = if keyword == "cube" {
+                    let s = entry.number()?;
+                    Point3D::new(s, s, s)
+                } else {
+                    entry.point()?
+                };
+                if size.x == 0. || size.y == 0. || size.z == 0. {
+                    return Err("size cannot be 0".to_string());
+                }
+
+                let mut cube = Cube::cuboid(&corner, &size, Color::WHITE);
+                let mut name = format!("{} {}", keyword, self.line);
+                let mut rotation = Quaternion::IDENTITY;
+                let mut two_sided = false;
+                let mut highlights = Highlights::default();
+                let mut texture = TextureOptions::default();
+
+                while let Some(option) = entry.option() {
+                    match option {
+                        "name" => name = entry.next("name")?.to_string(),
+                        "two_sided" => two_sided = true,
+                        "specular" | "shininess" => {
+                            highlights_option(option, entry, &mut highlights)?
+                        }
+                        "texture" | "filter" | "wrap" | "tile" => {
+                            texture_option(option, entry, &mut texture)?
+                        }
+                        "color" => cube.set_color(self.color(entry.next("colour")?)?),
+                        "face" => {
+                            let face = parse_face(entry.next("face")?)?;
+                            cube.set_face_color(face, self.color(entry.next("colour")?)?);
+                        }
+                        "rotate" => rotation = parse_rotation(&entry.point()?),
+                        _ => return Err(format!("unknown option `{}`", option)),
+                    }
+                }
+
+                let mut mesh = Mesh::from(cube.rotated_around_center(&rotation));
+                mesh.set_two_sided(two_sided);
+                mesh.set_specular(highlights.specular, highlights.shininess);
+                self.apply_texture(texture, &mut mesh)?;
+
+                self.level.objects.push(LevelObject {
+                    name,
+                    transform: Transform::IDENTITY,
+                    mesh,
+                });
+            }
+            "mesh" => {
+                let file = entry.next("file")?;
+                let mut name = file.to_string();
+                let mut color = Color::WHITE;
+                let mut transform = Transform::IDENTITY;
+                let mut two_sided = false;
+                let mut highlights = Highlights::default();
+                let mut texture = TextureOptions::default();
+
+                while let Some(option) = entry.option() {
+                    match option {
+                        "name" => name = entry.next("name")?.to_string(),
+                        "two_sided" => two_sided = true,
+                        "specular" | "shininess" => {
+                            highlights_option(option, entry, &mut highlights)?
+                        }
+                        "texture" | "filter" | "wrap" | "tile" => {
+                            texture_option(option, entry, &mut texture)?
+                        }
+                        "color" => color = self.color(entry.next("colour")?)?,
+                        "rotate" => transform.rotation = parse_rotation(&entry.point()?),
+                        "at" => transform.translation = entry.point()?,
+                        "scale" => transform.scale = entry.point()?,
+                        _ => return Err(format!("unknown option `{}`", option)),
+                    }
+                }
+
+                let path = self.path(file);
+                let extension = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_ascii_lowercase());
+
+                let mut mesh = match extension.as_deref() {
+                    Some("obj") => ObjModel::load(&path, color)
+                        .map(Mesh::from)
+                        .map_err(|e| e.to_string())?,
+                    Some("stl") => load_stl(&path, color).map_err(|e| match e {
+                        StlError::Io(..) => e.to_string(),
+                        _ => format!("{}: {}", path.display(), e),
+                    })?,
+                    _ => return Err(format!("`{}` is neither .obj nor .stl", file)),
+                };
+                mesh.set_two_sided(two_sided);
+                mesh.set_specular(highlights.specular, highlights.shininess);
+                if texture.file.is_some() && !mesh.has_uvs() {
+                    return Err(format!("`{}` has no texture coordinates", file));
+                }
+                self.apply_texture(texture, &mut mesh)?;
+
+                self.level.objects.push(LevelObject {
+                    name,
+                    transform,
+                    mesh,
+                });
+            }
+            "ambient" => {
+                self.level.ambient = entry.number()?;
+                if self.level.ambient < 0. {
+                    return Err("intensity cannot be negative".to_string());
+                }
+                self.ambient_set = true;
+            }
+            "light" => {
+                let kind = entry.next("light type")?;
+                let v = entry.point()?;
+
+                let direction = |d: Point3D| {
+                    if d.norm() == 0. {
+                        return Err("direction cannot be 0".to_string());
+                    }
+                    Ok(d)
+                };
+
+                let mut light = match kind {
+                    "directional" => Light::directional(&direction(v)?, Color::WHITE, 1.),
+                    "point" => Light::point(&v, Color::WHITE, 1.),
+                    "spot" => {
+                        let d = direction(entry.point()?)?;
+                        Light::spot(&v, &d, 30f32.to_radians(), Color::WHITE, 1.)
+                    }
+                    _ => return Err(format!("unknown light type `{}`", kind)),
+                };
+
+                while let Some(option) = entry.option() {
+                    match (option, &mut light.kind) {
+                        ("color", _) => light.color = self.color(entry.next("colour")?)?,
+                        ("intensity", _) => {
+                            light.intensity = entry.number()?;
+                            if light.intensity < 0. {
+                                return Err("intensity cannot be negative".to_string());
+                            }
+                        }
+                        ("attenuation", LightKind::Point { .. } | LightKind::Spot { .. }) => {
+                            let Point3D { x, y, z } = entry.point()?;
+                            if x < 0. || y < 0. || z < 0. || x + y + z == 0. {
+                                return Err("invalid attenuation".to_string());
+                            }
+                            light.attenuation = Attenuation::new(x, y, z);
+                        }
+                        ("angle", LightKind::Spot { inner, outer, .. }) => {
+                            let angle = entry.number()?;
+                            if angle <= 0. || angle >= 90. {
+                                return Err("the angle must be between 0 and 90".to_string());
+                            }
+                            *outer = angle.to_radians();
+                            *inner = *outer * 0.8;
+                        }
+                        _ => return Err(format!("unknown option `{}`", option)),
+                    }
+                }
+
+                self.level.lights.push(light);
+            }
+            "fog" => {
+                if self.level.fog.is_some() {
+                    return Err("there can only be one".to_string());
+                }
+
+                let kind = entry.next("fog type")?;
+                let mode = match kind {
+                    "linear" => {
+                        let (start, end) = (entry.number()?, entry.number()?);
+                        if start < 0. || end <= start {
+                            return Err("the end must be farther than the start".to_string());
+                        }
+                        FogMode::Linear { start, end }
+                    }
+                    "exp" | "exp2" => {
+                        let density = entry.number()?;
+                        if density <= 0. {
+                            return Err("the density must be positive".to_string());
+                        }
+                        if kind == "exp" {
+                            FogMode::Exponential { density }
+                        } else {
+                            FogMode::ExponentialSquared { density }
+                        }
+                    }
+                    _ => return Err(format!("unknown fog type `{}`", kind)),
+                };
+
+                let mut fog = Fog::new(mode, Color::BLACK);
+                while let Some(option) = entry.option() {
+                    match option {
+                        "color" => fog.color = self.color(entry.next("colour")?)?,
+                        "per_pixel" => fog.per_pixel = true,
+                        _ => return Err(format!("unknown option `{}`", option)),
+                    }
+                }
+                self.level.fog = Some(fog);
+            }
+            "falloff" => {
+                if self.level.falloff.is_some() {
+                    return Err("there can only be one".to_string());
+                }
+
+                let distance = entry.number()?;
+                if distance <= 0. {
+                    return Err("the distance must be positive".to_string());
+                }
+
+                let mut falloff = LightFalloff::new(distance, LightFalloff::DEFAULT_MIN);
+                while let Some(option) = entry.option() {
+                    match option {
+                        "min" => {
+                            falloff.min = entry.number()?;
+                            if !(0. ..=1.).contains(&falloff.min) {
+                                return Err("the minimum must be between 0 and 1".to_string());
+                            }
+                        }
+                        "per_pixel" => falloff.per_pixel = true,
+                        _ => return Err(format!("unknown option `{}`", option)),
+                    }
+                }
+                self.level.falloff = Some(falloff);
+            }
+            _ => return Err("unknown entry".to_string()),
+        }
+
+        Ok(())
+    }
+
+    fn color(&self, token: &str) -> Result<Color, String> {
+        parse_color(token, &self.colors).ok_or(format!("unknown colour `{}`", token))
+    }
+
+    // Files are relative to the level
+    fn path(&self, file: &str) -> PathBuf {
+        match self.base_dir {
+            Some(dir) => dir.join(file),
+            None => PathBuf::from(file),
+        }
+    }
+
+    fn apply_texture(&mut self, options: TextureOptions, mesh: &mut Mesh) -> Result<(), String> {
+        let Some(file) = options.file else {
+            if options.tile.is_some() {
+                return Err("`tile` needs a texture".to_string());
+            }
+            return Ok(());
+        };
+
+        let key = (self.path(&file), options.filter, options.wrap);
+        let texture = match self.textures.get(&key) {
+            Some(texture) => texture.clone(),
+            None => {
+                let texture = Texture::load(&key.0)
+                    .map_err(|e| e.to_string())?
+                    .with_filter(options.filter)
+                    .with_wrap(options.wrap);
+                let texture = Rc::new(texture);
+                self.textures.insert(key, texture.clone());
+                texture
+            }
+        };
+
+        if let Some((u, v)) = options.tile {
+            mesh.scale_uvs(u, v);
+        }
+        mesh.set_texture(Some(texture));
+        Ok(())
+    }
+}
+
+fn highlights_option(
+    option: &str,
+    entry: &mut Entry,
+    highlights: &mut Highlights,
+) -> Result<(), String> {
+    match option {
+        "specular" => {
+            highlights.specular = entry.number()?;
+            if highlights.specular < 0. {
+                return Err("specular strength cannot be negative".to_string());
+            }
+        }
+        "shininess" => {
+            highlights.shininess = entry.number()?;
+            if highlights.shininess <= 0. {
+                return Err("shininess must be positive".to_string());
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn texture_option(
+    option: &str,
+    entry: &mut Entry,
+    texture: &mut TextureOptions,
+) -> Result<(), String> {
+    match option {
+        "texture" => texture.file = Some(entry.next("file")?.to_string()),
+        "filter" => {
+            texture.filter = match entry.next("filter")? {
+                "nearest" => Filter::Nearest,
+                "bilinear" => Filter::Bilinear,
+                "trilinear" => Filter::Trilinear,
+                other => return Err(format!("unknown filter `{}`", other)),
+            }
+        }
+        "wrap" => {
+            texture.wrap = match entry.next("wrap mode")? {
+                "repeat" => Wrap::Repeat,
+                "clamp" => Wrap::Clamp,
+                other => return Err(format!("unknown wrap mode `{}`", other)),
+            }
+        }
+        "tile" => {
+            let (u, v) = (entry.number()?, entry.number()?);
+            if u == 0. || v == 0. {
+                return Err("tiling cannot be 0".to_string());
+            }
+            texture.tile = Some((u, v));
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+// Values of an entry, read from left to right
+struct Entry<'a> {
+    tokens: &'a [&'a str],
+    position: usize,
+}
+
+impl<'a> Entry<'a> {
+    fn new(tokens: &'a [&'a str]) -> Entry<'a> {
+        Entry {
+            tokens,
+            position: 0,
+        }
+    }
+
+    fn next(&mut self, what: &str) -> Result<&'a str, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or(format!("missing {}", what))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    // Name of the next option, if any is left
+    fn option(&mut self) -> Option<&'a str> {
+        self.next("").ok()
+    }
+
+    fn number(&mut self) -> Result<f32, String> {
+        let token = self.next("number")?;
+        token
+            .parse::<f32>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or(format!("invalid number `{}`", token))
+    }
+
+    fn point(&mut self) -> Result<Point3D, String> {
+        Ok(Point3D::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.tokens.get(self.position) {
+            Some(token) => Err(format!("unexpected `{}`", token)),
+            None => Ok(()),
+        }
+    }
+}
+
+// Angles in degrees around X, Y and Z
+fn parse_rotation(angles: &Point3D) -> Quaternion {
+    Quaternion::from_euler(&Rotation3::new(
+        angles.x.to_radians(),
+        angles.y.to_radians(),
+        angles.z.to_radians(),
+    ))
+}
+
+fn parse_face(token: &str) -> Result<CubeFace, String> {
+    match token {
+        "top" => Ok(CubeFace::Top),
+        "bottom" => Ok(CubeFace::Bottom),
+        "left" => Ok(CubeFace::Left),
+        "right" => Ok(CubeFace::Right),
+        "back" => Ok(CubeFace::Back),
+        "front" => Ok(CubeFace::Front),
+        _ => Err(format!("unknown face `{}`", token)),
+    }
+}
+
+// #rrggbb, a colour defined in the level, or one of the named ones
+fn parse_color(token: &str, defined: &HashMap<String, Color>) -> Option<Color> {
+    if let Some(hex) = token.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+
+    if let Some(color) = defined.get(token) {
+        return Some(*color);
+    }
+
+    match token {
+        "black" => Some(Color::BLACK),
+        "white" => Some(Color::WHITE),
+        "gray" | "grey" => Some(Color::GRAY),
+        "red" => Some(Color::RED),
+        "green" => Some(Color::GREEN),
+        "blue" => Some(Color::BLUE),
+        "yellow" => Some(Color::YELLOW),
+        "cyan" => Some(Color::CYAN),
+        "magenta" => Some(Color::MAGENTA),
+        _ => None,
+    }
+}
@@ -0,0 +1,55 @@
+mod bsp;
+mod camera;
+mod clip;
+mod cube;
+mod fog;
+mod framebuffer;
+mod image;
+mod level;
+mod light;
+mod material;
+mod matrix;
+mod mesh;
+mod obj;
+mod palette;
+mod point;
+mod quaternion;
+mod render;
+mod rotation;
+mod scene;
+mod shapes;
+mod square;
+mod stl;
+mod texture;
+mod transform;
+mod tuple;
+mod voxel;
+mod wad;
+
+pub use bsp::*;
+pub use camera::*;
+pub use clip::*;
+pub use cube::*;
+pub use fog::*;
+pub use framebuffer::*;
+pub use image::*;
+pub use level::*;
+pub use light::*;
+pub use material::*;
+pub use matrix::*;
+pub use mesh::*;
+pub use obj::*;
+pub use palette::*;
+pub use point::*;
+pub use quaternion::*;
+pub use render::*;
+pub use rotation::*;
+pub use scene::*;
+pub use shapes::*;
+pub use square::*;
+pub use stl::*;
+pub use texture::*;
+pub use transform::*;
+pub use tuple::*;
+pub use voxel::*;
+pub use wad::*;
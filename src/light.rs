@@ -0,0 +1,201 @@
+use sdl2::pixels::Color;
+
+use crate::Point3D;
+
+#[derive(Debug, Clone)]
+pub enum LightKind {
+    // Infinitely far away, like the sun: same direction everywhere
+    Directional {
+        direction: Point3D,
+    },
+    // Shines the same in every direction from `position`
+    Point {
+        position: Point3D,
+    },
+    // Cone along `direction`: full light within `inner` of its axis, fading
+    // out up to `outer` (both half-angles, in radians)
+    Spot {
+        position: Point3D,
+        direction: Point3D,
+        inner: f32,
+        outer: f32,
+    },
+}
+
+// Light left at distance d is 1 / (constant + linear * d + quadratic * d²)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    pub const NONE: Attenuation = Attenuation {
+        constant: 1.,
+        linear: 0.,
+        quadratic: 0.,
+    };
+
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Attenuation {
+        Attenuation {
+            constant,
+            linear,
+            quadratic,
+        }
+    }
+
+    pub fn factor(&self, distance: f32) -> f32 {
+        let divisor = self.constant + self.linear * distance + self.quadratic * distance * distance;
+        if divisor > 0. {
+            (1. / divisor).min(1.)
+        } else {
+            1.
+        }
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Attenuation {
+        Attenuation::NONE
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+    // Ignored by directional lights
+    pub attenuation: Attenuation,
+}
+
+impl Light {
+    pub fn directional(direction: &Point3D, color: Color, intensity: f32) -> Light {
+        Light {
+            kind: LightKind::Directional {
+                direction: direction.normalize(),
+            },
+            color,
+            intensity,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    pub fn point(position: &Point3D, color: Color, intensity: f32) -> Light {
+        Light {
+            kind: LightKind::Point {
+                position: position.clone(),
+            },
+            color,
+            intensity,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    pub fn spot(
+        position: &Point3D,
+        direction: &Point3D,
+        outer: f32,
+        color: Color,
+        intensity: f32,
+    ) -> Light {
+        Light {
+            kind: LightKind::Spot {
+                position: position.clone(),
+                direction: direction.normalize(),
+                inner: outer * 0.8,
+                outer,
+            },
+            color,
+            intensity,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Light {
+        self.attenuation = attenuation;
+        self
+    }
+
+    // Direction towards the light from `point` (normalized), and how much of
+    // the light gets there. None where it cannot tell
+    fn incoming(&self, point: &Point3D) -> Option<(Point3D, f32)> {
+        match &self.kind {
+            LightKind::Directional { direction } => Some((direction * -1., 1.)),
+            LightKind::Point { position } => {
+                let offset = position - point;
+                let distance = offset.norm();
+                if distance == 0. {
+                    return None;
+                }
+                Some((&offset * (1. / distance), self.attenuation.factor(distance)))
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                inner,
+                outer,
+            } => {
+                let offset = position - point;
+                let distance = offset.norm();
+                if distance == 0. {
+                    return None;
+                }
+                let to_light = &offset * (1. / distance);
+
+                let angle = (-direction.dot(&to_light)).clamp(-1., 1.).acos();
+                let cone = if angle <= *inner {
+                    1.
+                } else if angle >= *outer {
+                    0.
+                } else {
+                    (outer - angle) / (outer - inner)
+                };
+                Some((to_light, cone * self.attenuation.factor(distance)))
+            }
+        }
+    }
+
+    // Lambert diffuse term at `point` of a surface facing `normal`
+    // (normalized), for each of red, green and blue
+    pub fn diffuse(&self, point: &Point3D, normal: &Point3D) -> [f32; 3] {
+        let Some((to_light, strength)) = self.incoming(point) else {
+            return [0.; 3];
+        };
+
+        let lambert = normal.dot(&to_light).max(0.) * strength * self.intensity;
+        self.tinted(lambert)
+    }
+
+    // Blinn-Phong highlight seen from `eye`, sharper for a higher `shininess`
+    pub fn specular(
+        &self,
+        point: &Point3D,
+        normal: &Point3D,
+        eye: &Point3D,
+        shininess: f32,
+    ) -> [f32; 3] {
+        let Some((to_light, strength)) = self.incoming(point) else {
+            return [0.; 3];
+        };
+
+        let to_eye = eye - point;
+        let halfway = &to_light + &to_eye.normalize();
+        if normal.dot(&to_light) <= 0. || to_eye.norm() == 0. || halfway.norm() == 0. {
+            return [0.; 3];
+        }
+
+        let highlight = normal.dot(&halfway.normalize()).max(0.).powf(shininess);
+        self.tinted(highlight * strength * self.intensity)
+    }
+
+    fn tinted(&self, amount: f32) -> [f32; 3] {
+        let channel = |c: u8| amount * c as f32 / 255.;
+        [
+            channel(self.color.r),
+            channel(self.color.g),
+            channel(self.color.b),
+        ]
+    }
+}
@@ -1,37 +1,21 @@
 extern crate sdl2;
 
-mod cube;
-mod point;
-mod rotation;
-mod square;
-mod tuple;
-
-use cube::*;
-use point::*;
-use rotation::*;
-use square::*;
-use tuple::*;
-
-use lazy_static::lazy_static;
+use doom::*;
+
+mod options;
+
+use options::{Options, USAGE};
+
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use std::sync::{Arc, Mutex};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
 use std::time::Duration;
 
 use sdl2::gfx::primitives::DrawRenderer;
 
-// TODO: Home-made quaternions in Rust for better rotation
-
-fn color_mul(color: &Color, factor: f32) -> Color {
-    let r = (color.r as f32 * factor).round().clamp(0.0, 255.0) as u8;
-    let g = (color.g as f32 * factor).round().clamp(0.0, 255.0) as u8;
-    let b = (color.b as f32 * factor).round().clamp(0.0, 255.0) as u8;
-    let a = color.a;
-
-    Color { r, g, b, a }
-}
-
 macro_rules! rad {
     ($deg:expr) => {
         $deg * std::f32::consts::PI / 180.0
@@ -54,12 +38,20 @@ const FOV: f32 = rad!(75.0);
 const STEP: i32 = 20;
 const ANGLE_STEP: f32 = 5.;
 
-lazy_static! {
-    static ref PLAYER: Arc<Mutex<Point3D>> = Arc::new(Mutex::new(Point3D::new(0., 0., 0.)));
-    static ref ROTATION: Arc<Mutex<Rotation3>> = Arc::new(Mutex::new(Rotation3::new(0., 0., 0.)));
-}
-
 pub fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
     // let vertices0 = [
     //     Point3D::new(20., 20., 150.),
     //     Point3D::new(60., 20., 150.),
@@ -87,9 +79,111 @@ pub fn main() {
     //     Square::new(&vertices2, &Color::RED),
     // ];
 
-    let corner = Point3D::new(20., 20., 150.);
-    let cube = Cube::new(&corner, Color::CYAN, 20.);
-    let mut squares: Vec<Square> = cube.into();
+    let level = match (&options.level, &options.wad) {
+        (Some(path), _) => match Level::load(path) {
+            Ok(level) => level,
+            Err(e) => {
+                eprintln!("could not load the level: {}", e);
+                process::exit(1);
+            }
+        },
+        (None, Some(path)) => match load_wad_level(path, options.map.as_deref()) {
+            Ok(level) => level,
+            Err(e) => {
+                eprintln!("could not load the map: {}", e);
+                process::exit(1);
+            }
+        },
+        (None, None) => {
+            let corner = Point3D::new(20., 20., 150.);
+            let cube = Cube::new(&corner, Color::CYAN, 20.);
+            Level {
+                objects: vec![LevelObject {
+                    name: "cube".to_string(),
+                    transform: Transform::IDENTITY,
+                    mesh: Mesh::from(cube),
+                }],
+                lights: vec![Light::directional(
+                    &Point3D::new(1., 2., 1.5),
+                    Color::WHITE,
+                    0.8,
+                )],
+                ambient: 0.25,
+                ..Level::default()
+            }
+        }
+    };
+
+    let mut scene = level.scene();
+
+    let mut meshes = scene.world_meshes();
+
+    let mut camera = Camera::new(
+        options.position.clone().unwrap_or(level.spawn.clone()),
+        Quaternion::IDENTITY,
+        FOV,
+        options.width,
+        options.height,
+    );
+    camera.yaw(options.yaw.map_or(level.spawn_yaw, |a| rad!(a)));
+    camera.pitch(options.pitch.map_or(level.spawn_pitch, |a| rad!(a)));
+
+    // Far away, faces fade into the fog: so does the background
+    let background = level.fog.as_ref().map_or(Color::BLACK, |f| f.color);
+    let mut renderer = Renderer::new(background);
+    renderer.lights = level.lights.clone();
+    renderer.ambient = level.ambient;
+    renderer.fog = level.fog.clone();
+    renderer.falloff = match options.falloff {
+        None => level.falloff.clone(),
+        Some(distance) if distance > 0. => Some(LightFalloff {
+            per_pixel: true,
+            ..LightFalloff::new(distance, LightFalloff::DEFAULT_MIN)
+        }),
+        Some(_) => None,
+    };
+    renderer.shading = options.shading;
+    renderer.mip_debug = options.mip_debug;
+    renderer.mode = options.mode;
+    // Only generated if needed, which takes a moment
+    let mut palette = level.palette.clone();
+    let mut indexed = || {
+        palette
+            .get_or_insert_with(|| Rc::new(Palette::generate()))
+            .clone()
+    };
+    if options.indexed {
+        renderer.palette = Some(indexed());
+    }
+
+    // Levels do not move, so their tree is built once
+    let bsp = options.painter.then(|| BspTree::new(&meshes));
+
+    if let Some(path) = &options.export_stl {
+        if let Err(e) = save_stl(&meshes, path, true) {
+            eprintln!("could not export the scene: {}", e);
+            process::exit(1);
+        }
+    }
+
+    if let Some(path) = &options.headless {
+        let mut framebuffer = Framebuffer::new(camera.width, camera.height);
+        framebuffer.set_depth_test(bsp.is_none());
+        match &bsp {
+            Some(bsp) => renderer.render_bsp(&camera, bsp, &mut framebuffer),
+            None => renderer.render(&camera, &meshes, &mut framebuffer),
+        }
+        if let Err(e) = save_image(&framebuffer, path) {
+            eprintln!("could not write {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+
+    if options.headless.is_some() || options.export_stl.is_some() {
+        return;
+    }
+
+    // The window keeps its size, a custom --size only changes the rendering resolution
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
@@ -106,80 +200,33 @@ pub fn main() {
     canvas.clear();
     canvas.present();
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
-
-    'running: loop {
-	let cur_rotation = {
-            let r = ROTATION.lock().unwrap();
-            r.clone()
-	};
-
-        // Draw the background
-        canvas.set_draw_color(Color::BLACK);
-        canvas.clear();
-
-        let rot_revert = &cur_rotation.revert();
-	
-        let (dir_x, dir_z) = (
-            Point3D::X.rotate(&Point3D::ZERO, &rot_revert),
-            Point3D::Z.rotate(&Point3D::ZERO, &rot_revert),
-        );
-
-        let p = {
-            let p = PLAYER.lock().unwrap();
-            p.clone()
-        };
-
-        squares.sort_by(|s1, s2| Square::closer_to_point(s2, s1, &p));
-
-        // println!("");
-        for s in squares.iter_mut() {
-            let cross = s.normal().dot(&dir_z);
-
-            // {
-            //     let p = PLAYER.lock().unwrap();
-            //     println!("distance: {}", s.distance_from_point(&p));
-            // }
-
-            if cross > 0. {
-                continue;
-            }
-
-            // let mut vertices = [Point3D::ZERO; 4];
-
-            // for (i, v) in s.vertices.iter().enumerate() {
-            //     vertices[i] = v.rotate(&p, &cur_rotation);
-            // }
-
-            let a = s.normal().angle(&dir_z);
-            let lumen = a / std::f32::consts::PI;
-
-            let sq = Square::new(&s.vertices, &s.color);
-            canvas.set_draw_color(sq.color);
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGBA32, camera.width, camera.height)
+        .unwrap();
 
-            let mut vx: Vec<i16> = vec![];
-            let mut vy: Vec<i16> = vec![];
+    let mut framebuffer = Framebuffer::new(camera.width, camera.height);
+    // The BSP tree gives the faces in order, so the depth test is not needed
+    framebuffer.set_depth_test(bsp.is_none());
 
-            s.vertices.iter().for_each(|v| {
-                let v2: Point2D = v.into();
-                vx.push(v2.x as i16);
-                vy.push(v2.y as i16);
-            });
+    let mut event_pump = sdl_context.event_pump().unwrap();
 
-            let _ =
-                canvas.filled_polygon(vx.as_slice(), vy.as_slice(), color_mul(&sq.color, lumen));
+    'running: loop {
+        let (dir_x, dir_z) = (camera.right(), camera.forward());
 
-            // for vertices in sq.iter_pairs() {
-            //     let first = vertices.first;
-            //     let second = vertices.second;
+        // Only rebuilt when something moved
+        if scene.update_world_transforms() {
+            meshes = scene.world_meshes();
+        }
 
-            //     let _ = canvas.draw_line(
-            //         sdl2::rect::Point::from(&Point2D::from(first)),
-            //         sdl2::rect::Point::from(&Point2D::from(second)),
-            //     );
-            // }
+        match &bsp {
+            Some(bsp) => renderer.render_bsp(&camera, bsp, &mut framebuffer),
+            None => renderer.render(&camera, &meshes, &mut framebuffer),
         }
 
+        let _ = texture.update(None, framebuffer.pixels(), framebuffer.pitch());
+        let _ = canvas.copy(&texture, None, None);
+
 	// Drawing the crosshair
 	let _ = canvas.filled_circle((WINDOW_WIDTH/2) as i16, (WINDOW_HEIGHT/2) as i16, 5, Color::RED);
 	
@@ -194,77 +241,88 @@ pub fn main() {
                     keycode: Some(Keycode::Z),
                     ..
                 } => {
-                    let mut p = PLAYER.lock().unwrap();
-                    let d = &dir_z * STEP;
-                    *p += d;
+                    camera.translate(&(&dir_z * STEP));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::S),
                     ..
                 } => {
-                    let mut p = PLAYER.lock().unwrap();
-                    let d = &dir_z * (-STEP);
-                    *p += d;
+                    camera.translate(&(&dir_z * (-STEP)));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::D),
                     ..
                 } => {
-                    let mut p = PLAYER.lock().unwrap();
-                    let d = &dir_x * STEP;
-                    *p += d;
+                    camera.translate(&(&dir_x * STEP));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::Q),
                     ..
                 } => {
-                    let mut p = PLAYER.lock().unwrap();
-                    let d = &dir_x * (-STEP);
-                    *p += d;
+                    camera.translate(&(&dir_x * (-STEP)));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::A),
                     ..
                 } => {
-                    let mut p = PLAYER.lock().unwrap();
-                    let d = &Point3D::Y * STEP;
-                    *p += d;
+                    camera.translate(&(&Point3D::Y * STEP));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::E),
                     ..
                 } => {
-                    let mut p = PLAYER.lock().unwrap();
-                    let d = &Point3D::Y * (-STEP);
-                    *p += d;
+                    camera.translate(&(&Point3D::Y * (-STEP)));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::W),
                     ..
                 } => {
-                    let mut r = ROTATION.lock().unwrap();
-                    *r += Rotation3::new_axis(rad!(ANGLE_STEP), Rotation::Y);
+                    camera.yaw(-rad!(ANGLE_STEP));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::X),
                     ..
                 } => {
-                    let mut r = ROTATION.lock().unwrap();
-                    *r -= Rotation3::new_axis(rad!(ANGLE_STEP), Rotation::Y);
+                    camera.yaw(rad!(ANGLE_STEP));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::T),
                     ..
                 } => {
-                    let mut r = ROTATION.lock().unwrap();
-                    *r += Rotation3::new_axis(rad!(ANGLE_STEP), Rotation::X);
+                    camera.pitch(-rad!(ANGLE_STEP));
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::R),
                     ..
                 } => {
-                    let mut r = ROTATION.lock().unwrap();
-                    *r -= Rotation3::new_axis(rad!(ANGLE_STEP), Rotation::X);
+                    camera.pitch(rad!(ANGLE_STEP));
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    ..
+                } => {
+                    renderer.shading = renderer.shading.next();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::M),
+                    ..
+                } => {
+                    renderer.mip_debug = !renderer.mip_debug;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::V),
+                    ..
+                } => {
+                    renderer.mode = renderer.mode.next();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    ..
+                } => {
+                    renderer.palette = match renderer.palette {
+                        Some(_) => None,
+                        None => Some(indexed()),
+                    };
                 }
                 _ => {}
             }
@@ -274,3 +332,27 @@ pub fn main() {
         ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
     }
 }
+
+// The given map, or the first one of the WAD
+fn load_wad_level(path: &Path, map: Option<&str>) -> Result<Level, WadError> {
+    let wad = Wad::load(path)?;
+    let name = match map {
+        Some(name) => name.to_string(),
+        None => wad
+            .map_names()
+            .into_iter()
+            .next()
+            .ok_or(WadError::Format("no map".to_string()))?,
+    };
+
+    let mut level = wad.map(&name)?.level();
+    // Only needed for indexed rendering, which falls back to a generated palette
+    level.palette = match wad.palette() {
+        Ok(palette) => palette.map(Rc::new),
+        Err(e) => {
+            eprintln!("ignoring the palette of the WAD: {}", e);
+            None
+        }
+    };
+    Ok(level)
+}
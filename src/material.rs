@@ -0,0 +1,72 @@
+use std::rc::Rc;
+
+use sdl2::pixels::Color;
+
+use crate::{TexCoord, Texture};
+
+// How a face looks. Faces are only drawn from the side their normal points
+// to, unless they are two-sided
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub color: Color,
+    pub two_sided: bool,
+    // Strength of the highlights, 0 for none
+    pub specular: f32,
+    // Higher is smaller, sharper highlights
+    pub shininess: f32,
+    // Multiplied by `color`, on meshes with texture coordinates
+    pub texture: Option<Rc<Texture>>,
+}
+
+impl Material {
+    // No highlights unless asked for
+    pub const DEFAULT_SPECULAR: f32 = 0.;
+    pub const DEFAULT_SHININESS: f32 = 32.;
+
+    pub fn new(color: Color) -> Material {
+        Material {
+            color,
+            two_sided: false,
+            specular: Self::DEFAULT_SPECULAR,
+            shininess: Self::DEFAULT_SHININESS,
+            texture: None,
+        }
+    }
+
+    pub fn two_sided(color: Color) -> Material {
+        Material {
+            two_sided: true,
+            ..Material::new(color)
+        }
+    }
+
+    // The texture as it is, untinted
+    pub fn textured(texture: Rc<Texture>) -> Material {
+        Material {
+            texture: Some(texture),
+            ..Material::new(Color::WHITE)
+        }
+    }
+
+    // Colour at `coord` in the texture, or the plain colour without a texture
+    pub fn color_at(&self, coord: &TexCoord) -> Color {
+        let Some(texture) = &self.texture else {
+            return self.color;
+        };
+
+        let texel = texture.sample_at(coord);
+        let tint = |t: u8, c: u8| ((t as u32 * c as u32 + 127) / 255) as u8;
+        Color::RGBA(
+            tint(texel.r, self.color.r),
+            tint(texel.g, self.color.g),
+            tint(texel.b, self.color.b),
+            tint(texel.a, self.color.a),
+        )
+    }
+}
+
+impl From<Color> for Material {
+    fn from(value: Color) -> Material {
+        Material::new(value)
+    }
+}
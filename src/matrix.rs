@@ -0,0 +1,237 @@
+use crate::{Point3D, Quaternion};
+use std::ops::{Mul, MulAssign};
+
+// Row-major 4x4 matrix acting on column vectors: `a * b` applies `b` first, then `a`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ],
+    };
+
+    pub fn new(m: [[f32; 4]; 4]) -> Mat4 {
+        Mat4 { m }
+    }
+
+    pub fn translation(t: &Point3D) -> Mat4 {
+        Mat4::new([
+            [1., 0., 0., t.x],
+            [0., 1., 0., t.y],
+            [0., 0., 1., t.z],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn scale(s: &Point3D) -> Mat4 {
+        Mat4::new([
+            [s.x, 0., 0., 0.],
+            [0., s.y, 0., 0.],
+            [0., 0., s.z, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn rotation(q: &Quaternion) -> Mat4 {
+        let Quaternion { w, x, y, z } = q.normalize();
+
+        Mat4::new([
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - w * z),
+                2. * (x * z + w * y),
+                0.,
+            ],
+            [
+                2. * (x * y + w * z),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - w * x),
+                0.,
+            ],
+            [
+                2. * (x * z - w * y),
+                2. * (y * z + w * x),
+                1. - 2. * (x * x + y * y),
+                0.,
+            ],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    // Camera looks down +Z: x and y end up in [-1, 1], depth in [0, 1] between `near` and `far`
+    pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1. / (fov / 2.).tan();
+        let range = far / (far - near);
+
+        Mat4::new([
+            [f / aspect, 0., 0., 0.],
+            [0., f, 0., 0.],
+            [0., 0., range, -near * range],
+            [0., 0., 1., 0.],
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, v) in row.iter_mut().enumerate() {
+                *v = self.m[c][r];
+            }
+        }
+
+        Mat4 { m }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.sub_determinants();
+
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    // None if the matrix is singular
+    pub fn inverse(&self) -> Option<Mat4> {
+        let a = &self.m;
+        let (s, c) = self.sub_determinants();
+
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inv = [
+            [
+                a[1][1] * c[5] - a[1][2] * c[4] + a[1][3] * c[3],
+                -a[0][1] * c[5] + a[0][2] * c[4] - a[0][3] * c[3],
+                a[3][1] * s[5] - a[3][2] * s[4] + a[3][3] * s[3],
+                -a[2][1] * s[5] + a[2][2] * s[4] - a[2][3] * s[3],
+            ],
+            [
+                -a[1][0] * c[5] + a[1][2] * c[2] - a[1][3] * c[1],
+                a[0][0] * c[5] - a[0][2] * c[2] + a[0][3] * c[1],
+                -a[3][0] * s[5] + a[3][2] * s[2] - a[3][3] * s[1],
+                a[2][0] * s[5] - a[2][2] * s[2] + a[2][3] * s[1],
+            ],
+            [
+                a[1][0] * c[4] - a[1][1] * c[2] + a[1][3] * c[0],
+                -a[0][0] * c[4] + a[0][1] * c[2] - a[0][3] * c[0],
+                a[3][0] * s[4] - a[3][1] * s[2] + a[3][3] * s[0],
+                -a[2][0] * s[4] + a[2][1] * s[2] - a[2][3] * s[0],
+            ],
+            [
+                -a[1][0] * c[3] + a[1][1] * c[1] - a[1][2] * c[0],
+                a[0][0] * c[3] - a[0][1] * c[1] + a[0][2] * c[0],
+                -a[3][0] * s[3] + a[3][1] * s[1] - a[3][2] * s[0],
+                a[2][0] * s[3] - a[2][1] * s[1] + a[2][2] * s[0],
+            ],
+        ];
+
+        let mut m = [[0.; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, v) in row.iter_mut().enumerate() {
+                *v = inv[r][c] / det;
+            }
+        }
+
+        Some(Mat4 { m })
+    }
+
+    // 2x2 determinants of the two upper rows (s) and the two lower rows (c)
+    fn sub_determinants(&self) -> ([f32; 6], [f32; 6]) {
+        let a = &self.m;
+
+        let s = [
+            a[0][0] * a[1][1] - a[1][0] * a[0][1],
+            a[0][0] * a[1][2] - a[1][0] * a[0][2],
+            a[0][0] * a[1][3] - a[1][0] * a[0][3],
+            a[0][1] * a[1][2] - a[1][1] * a[0][2],
+            a[0][1] * a[1][3] - a[1][1] * a[0][3],
+            a[0][2] * a[1][3] - a[1][2] * a[0][3],
+        ];
+
+        let c = [
+            a[2][0] * a[3][1] - a[3][0] * a[2][1],
+            a[2][0] * a[3][2] - a[3][0] * a[2][2],
+            a[2][0] * a[3][3] - a[3][0] * a[2][3],
+            a[2][1] * a[3][2] - a[3][1] * a[2][2],
+            a[2][1] * a[3][3] - a[3][1] * a[2][3],
+            a[2][2] * a[3][3] - a[3][2] * a[2][3],
+        ];
+
+        (s, c)
+    }
+
+    // Points are affected by translation, and get divided by w for projective matrices
+    pub fn transform_point(&self, p: &Point3D) -> Point3D {
+        let [x, y, z, w] = self.apply([p.x, p.y, p.z, 1.]);
+
+        if w != 0. && w != 1. {
+            Point3D::new(x / w, y / w, z / w)
+        } else {
+            Point3D::new(x, y, z)
+        }
+    }
+
+    // Directions ignore translation
+    pub fn transform_direction(&self, d: &Point3D) -> Point3D {
+        let [x, y, z, _] = self.apply([d.x, d.y, d.z, 0.]);
+        Point3D::new(x, y, z)
+    }
+
+    // Normals need the inverse transpose to stay perpendicular under non-uniform scale
+    pub fn transform_normal(&self, n: &Point3D) -> Point3D {
+        self.normal_matrix().transform_direction(n)
+    }
+
+    // Matrix `transform_normal` uses, to compute once when moving many normals
+    pub fn normal_matrix(&self) -> Mat4 {
+        match self.inverse() {
+            Some(inv) => inv.transpose(),
+            None => self.clone(),
+        }
+    }
+
+    pub fn apply(&self, v: [f32; 4]) -> [f32; 4] {
+        let mut out = [0.; 4];
+        for (o, row) in out.iter_mut().zip(self.m.iter()) {
+            *o = row[0] * v[0] + row[1] * v[1] + row[2] * v[2] + row[3] * v[3];
+        }
+
+        out
+    }
+}
+
+impl Mul for &Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: &Mat4) -> Mat4 {
+        let mut m = [[0.; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, v) in row.iter_mut().enumerate() {
+                *v = (0..4).map(|k| self.m[r][k] * rhs.m[k][c]).sum();
+            }
+        }
+
+        Mat4 { m }
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        &self * &rhs
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, rhs: Mat4) {
+        *self = &*self * &rhs
+    }
+}
@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+
+use crate::{Mat4, Material, Point3D, Square, Texture};
+
+// Polygon referencing the mesh's vertex buffer. Corners are listed so that the
+// normal (see `Mesh::face_normal`) points outwards
+#[derive(Debug, Clone)]
+pub struct Face {
+    pub indices: Vec<usize>,
+    pub material: Material,
+}
+
+impl Face {
+    pub fn new<M: Into<Material>>(indices: &[usize], material: M) -> Face {
+        Face {
+            indices: indices.to_vec(),
+            material: material.into(),
+        }
+    }
+}
+
+// `normals` and `uvs` are optional per vertex attributes: each is either empty
+// or as long as `vertices`. UVs have v pointing down, like image rows
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Point3D>,
+    pub faces: Vec<Face>,
+    pub normals: Vec<Point3D>,
+    pub uvs: Vec<(f32, f32)>,
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh::default()
+    }
+
+    // Returns the index to use in faces
+    pub fn add_vertex(&mut self, vertex: Point3D) -> usize {
+        self.vertices.push(vertex);
+        self.vertices.len() - 1
+    }
+
+    // Takes a `Material` or just a `Color`
+    pub fn add_face<M: Into<Material>>(&mut self, indices: &[usize], material: M) -> usize {
+        assert!(indices.len() >= 3, "a face needs at least 3 vertices");
+        assert!(
+            indices.iter().all(|i| *i < self.vertices.len()),
+            "face index out of bounds"
+        );
+
+        self.faces.push(Face::new(indices, material));
+        self.faces.len() - 1
+    }
+
+    pub fn add_triangle<M: Into<Material>>(
+        &mut self,
+        a: usize,
+        b: usize,
+        c: usize,
+        material: M,
+    ) -> usize {
+        self.add_face(&[a, b, c], material)
+    }
+
+    pub fn set_two_sided(&mut self, two_sided: bool) {
+        for face in self.faces.iter_mut() {
+            face.material.two_sided = two_sided;
+        }
+    }
+
+    // Same highlights on every face, see `Material`
+    pub fn set_specular(&mut self, specular: f32, shininess: f32) {
+        for face in self.faces.iter_mut() {
+            face.material.specular = specular;
+            face.material.shininess = shininess;
+        }
+    }
+
+    // Same texture on every face. Only shows with texture coordinates
+    pub fn set_texture(&mut self, texture: Option<Rc<Texture>>) {
+        for face in self.faces.iter_mut() {
+            face.material.texture = texture.clone();
+        }
+    }
+
+    // Texture coordinates multiplied by `u` and `v`, for a repeating texture
+    // to tile more or less
+    pub fn scale_uvs(&mut self, u: f32, v: f32) {
+        for uv in self.uvs.iter_mut() {
+            *uv = (uv.0 * u, uv.1 * v);
+        }
+    }
+
+    pub fn has_normals(&self) -> bool {
+        !self.vertices.is_empty() && self.normals.len() == self.vertices.len()
+    }
+
+    pub fn has_uvs(&self) -> bool {
+        !self.vertices.is_empty() && self.uvs.len() == self.vertices.len()
+    }
+
+    // Copies the other mesh's vertices and faces into this one. Vertex attributes
+    // are only kept when both meshes have them
+    pub fn append(&mut self, other: &Mesh) {
+        let offset = self.vertices.len();
+
+        if (offset == 0 || self.has_normals()) && other.has_normals() {
+            self.normals.extend(other.normals.iter().cloned());
+        } else {
+            self.normals.clear();
+        }
+
+        if (offset == 0 || self.has_uvs()) && other.has_uvs() {
+            self.uvs.extend(other.uvs.iter().cloned());
+        } else {
+            self.uvs.clear();
+        }
+
+        self.vertices.extend(other.vertices.iter().cloned());
+        self.faces.extend(other.faces.iter().map(|f| Face {
+            indices: f.indices.iter().map(|i| i + offset).collect(),
+            ..f.clone()
+        }));
+    }
+
+    pub fn face_vertices(&self, face: &Face) -> Vec<Point3D> {
+        face.indices
+            .iter()
+            .map(|i| self.vertices[*i].clone())
+            .collect()
+    }
+
+    // Same as `Square::normal`: not normalized, and pointing outwards when the
+    // corners are given in the right order
+    pub fn face_normal(&self, face: &Face) -> Point3D {
+        let v0 = &self.vertices[face.indices[0]];
+        let first = &self.vertices[face.indices[1]] - v0;
+        let second = &self.vertices[face.indices[face.indices.len() - 1]] - v0;
+
+        first * second
+    }
+
+    // The mesh's own normals, or else each vertex gets the average of the
+    // normals of the faces around it, weighted by their area
+    pub fn vertex_normals(&self) -> Cow<'_, [Point3D]> {
+        if self.has_normals() {
+            return Cow::Borrowed(&self.normals);
+        }
+
+        let mut normals = vec![Point3D::ZERO; self.vertices.len()];
+        for face in self.faces.iter() {
+            let normal = self.face_normal(face);
+            for i in face.indices.iter() {
+                normals[*i] += normal.clone();
+            }
+        }
+
+        for n in normals.iter_mut() {
+            if n.norm() > 0. {
+                *n = n.normalize();
+            }
+        }
+
+        Cow::Owned(normals)
+    }
+
+    // Fills `normals` when the mesh has none, for `vertex_normals` not to
+    // compute them again every time
+    pub fn compute_normals(&mut self) {
+        if !self.has_normals() {
+            self.normals = self.vertex_normals().into_owned();
+        }
+    }
+
+    // Normalized normal of every face
+    pub fn face_normals(&self) -> Vec<Point3D> {
+        self.faces
+            .iter()
+            .map(|f| self.face_normal(f).normalize())
+            .collect()
+    }
+
+    // Copy moved by `matrix`. Mirroring matrices flip the faces' corner order so
+    // that normals keep pointing outwards
+    pub fn transformed(&self, matrix: &Mat4) -> Mesh {
+        let mirrored = matrix.determinant() < 0.;
+        let normal_matrix = matrix.normal_matrix();
+
+        Mesh {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|v| matrix.transform_point(v))
+                .collect(),
+            faces: self
+                .faces
+                .iter()
+                .map(|f| {
+                    let mut face = f.clone();
+                    if mirrored {
+                        face.indices.reverse();
+                    }
+                    face
+                })
+                .collect(),
+            normals: self
+                .normals
+                .iter()
+                .map(|n| {
+                    let n = normal_matrix.transform_direction(n);
+                    if n.norm() > 0. {
+                        n.normalize()
+                    } else {
+                        n
+                    }
+                })
+                .collect(),
+            uvs: self.uvs.clone(),
+        }
+    }
+
+    // Faces split as triangle fans, keeping their winding
+    pub fn triangles(&self) -> impl Iterator<Item = ([usize; 3], &Face)> + '_ {
+        self.faces.iter().flat_map(|f| {
+            (1..f.indices.len() - 1)
+                .map(move |i| ([f.indices[0], f.indices[i], f.indices[i + 1]], f))
+        })
+    }
+}
+
+impl From<&Square> for Mesh {
+    fn from(value: &Square) -> Mesh {
+        let mut mesh = Mesh::new();
+        let indices: Vec<usize> = value
+            .vertices
+            .iter()
+            .map(|v| mesh.add_vertex(v.clone()))
+            .collect();
+        mesh.add_face(&indices, value.color);
+
+        mesh
+    }
+}
+
+impl From<&[Square]> for Mesh {
+    fn from(value: &[Square]) -> Mesh {
+        let mut mesh = Mesh::new();
+        for s in value {
+            mesh.append(&Mesh::from(s));
+        }
+
+        mesh
+    }
+}
@@ -0,0 +1,396 @@
+// Wavefront OBJ/MTL loader
+//
+// OBJ files are Y up with the camera looking down -Z, while we are Y down
+// looking down +Z: coordinates are turned half a turn around X, (x, y, z) ->
+// (x, -y, -z), which keeps counter-clockwise faces pointing outwards
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sdl2::pixels::Color;
+
+use crate::{Mesh, Point3D};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, io::Error),
+    Parse {
+        file: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ObjError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io(_, e) => Some(e),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+// Faces of an OBJ group (`g` or `o`), already triangulated
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+}
+
+impl ObjModel {
+    // Faces without a material get `default_color`
+    pub fn load(path: &Path, default_color: Color) -> Result<ObjModel, ObjError> {
+        let source = fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+
+        ObjParser::new(&path.display().to_string(), path.parent(), default_color).parse(&source)
+    }
+
+    // `mtllib` statements are resolved relatively to `base_dir`, and ignored without one
+    pub fn parse(
+        source: &str,
+        base_dir: Option<&Path>,
+        default_color: Color,
+    ) -> Result<ObjModel, ObjError> {
+        ObjParser::new("<obj>", base_dir, default_color).parse(source)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Mesh> {
+        self.groups.iter().find(|g| g.name == name).map(|g| &g.mesh)
+    }
+
+    // All groups merged together
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new();
+        for group in self.groups.iter() {
+            mesh.append(&group.mesh);
+        }
+
+        mesh
+    }
+}
+
+impl From<ObjModel> for Mesh {
+    fn from(value: ObjModel) -> Mesh {
+        value.to_mesh()
+    }
+}
+
+// Diffuse colour of every material of a MTL file
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, Color>, ObjError> {
+    let source = fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+    parse_mtl(&source, &path.display().to_string())
+}
+
+pub fn parse_mtl(source: &str, file: &str) -> Result<HashMap<String, Color>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| ObjError::Parse {
+            file: file.to_string(),
+            line: i + 1,
+            message,
+        };
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("newmtl") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                if name.is_empty() {
+                    return Err(error("newmtl without a name".to_string()));
+                }
+                materials.insert(name.clone(), Color::WHITE);
+                current = Some(name);
+            }
+            Some("Kd") => {
+                let Some(name) = &current else {
+                    return Err(error("Kd before any newmtl".to_string()));
+                };
+                let rgb = parse_floats(tokens, 3).map_err(error)?;
+                let channel = |c: f32| (c.clamp(0., 1.) * 255.).round() as u8;
+                materials.insert(
+                    name.clone(),
+                    Color::RGB(channel(rgb[0]), channel(rgb[1]), channel(rgb[2])),
+                );
+            }
+            // Everything else (comments, specular, textures...) is not used
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+// (position, texture coordinate, normal) indices of a face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct ObjParser<'a> {
+    file: String,
+    line: usize,
+    base_dir: Option<&'a Path>,
+    default_color: Color,
+
+    positions: Vec<Point3D>,
+    normals: Vec<Point3D>,
+    uvs: Vec<(f32, f32)>,
+    materials: HashMap<String, Color>,
+    color: Color,
+
+    groups: Vec<ObjGroup>,
+    current: usize,
+    // Vertex index of each corner already seen, for every group's mesh
+    vertex_maps: Vec<HashMap<Corner, usize>>,
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(file: &str, base_dir: Option<&'a Path>, default_color: Color) -> ObjParser<'a> {
+        ObjParser {
+            file: file.to_string(),
+            line: 0,
+            base_dir,
+            default_color,
+            positions: vec![],
+            normals: vec![],
+            uvs: vec![],
+            materials: HashMap::new(),
+            color: default_color,
+            groups: vec![],
+            current: 0,
+            vertex_maps: vec![],
+        }
+    }
+
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            file: self.file.clone(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn parse(mut self, source: &str) -> Result<ObjModel, ObjError> {
+        self.select_group("default");
+
+        for (i, line) in source.lines().enumerate() {
+            self.line = i + 1;
+            self.parse_line(line)?;
+        }
+
+        // Attributes that never appear in the file are left out rather than zeroed.
+        // So are the normals of a group with corners without one: they are then
+        // computed from the faces, for every corner alike
+        let (normals, uvs) = (!self.normals.is_empty(), !self.uvs.is_empty());
+
+        let groups = self
+            .groups
+            .into_iter()
+            .zip(self.vertex_maps.iter())
+            .filter(|(g, _)| !g.mesh.faces.is_empty())
+            .map(|(mut g, corners)| {
+                if !normals || corners.keys().any(|(_, _, n)| n.is_none()) {
+                    g.mesh.normals.clear();
+                }
+                if !uvs {
+                    g.mesh.uvs.clear();
+                }
+                g
+            })
+            .collect();
+
+        Ok(ObjModel { groups })
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), ObjError> {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else {
+            return Ok(());
+        };
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(tokens.take(3), 3).map_err(|e| self.error(e))?;
+                self.positions.push(Point3D::new(v[0], -v[1], -v[2]));
+            }
+            "vn" => {
+                let v = parse_floats(tokens, 3).map_err(|e| self.error(e))?;
+                self.normals.push(Point3D::new(v[0], -v[1], -v[2]));
+            }
+            "vt" => {
+                // v defaults to 0, and the optional w coordinate is ignored
+                let tokens: Vec<&str> = tokens.collect();
+                if !(1..=3).contains(&tokens.len()) {
+                    return Err(
+                        self.error(format!("expected 1 to 3 numbers, got {}", tokens.len()))
+                    );
+                }
+                let v = parse_floats(tokens.iter().copied(), tokens.len())
+                    .map_err(|e| self.error(e))?;
+                self.uvs.push((v[0], 1. - v.get(1).copied().unwrap_or(0.)));
+            }
+            "f" => {
+                let corners = tokens
+                    .map(|t| self.parse_corner(t))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if corners.len() < 3 {
+                    return Err(self.error(format!("face with {} vertices", corners.len())));
+                }
+                self.add_face(&corners);
+            }
+            "g" | "o" => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                self.select_group(if name.is_empty() { "default" } else { &name });
+            }
+            "usemtl" => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                self.color = match self.materials.get(&name) {
+                    Some(color) => *color,
+                    // Without a directory the material libraries could not be read
+                    None if self.base_dir.is_none() => self.default_color,
+                    None => return Err(self.error(format!("unknown material `{}`", name))),
+                };
+            }
+            "mtllib" => {
+                if let Some(dir) = self.base_dir {
+                    for name in tokens {
+                        self.materials.extend(load_mtl(&dir.join(name))?);
+                    }
+                }
+            }
+            // Smoothing groups, lines, points, free-form geometry and whatever
+            // else exporters write are not supported
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // `v`, `v/vt`, `v//vn` or `v/vt/vn`, 1-based or negative (relative to the end)
+    fn parse_corner(&self, token: &str) -> Result<Corner, ObjError> {
+        let mut parts = token.split('/');
+
+        let position = self.parse_index(parts.next(), self.positions.len(), "vertex")?;
+        let uv = match parts.next() {
+            None | Some("") => None,
+            Some(t) => Some(self.parse_index(Some(t), self.uvs.len(), "texture coordinate")?),
+        };
+        let normal = match parts.next() {
+            None | Some("") => None,
+            Some(t) => Some(self.parse_index(Some(t), self.normals.len(), "normal")?),
+        };
+
+        if parts.next().is_some() {
+            return Err(self.error(format!("invalid face vertex `{}`", token)));
+        }
+
+        Ok((position, uv, normal))
+    }
+
+    fn parse_index(
+        &self,
+        token: Option<&str>,
+        count: usize,
+        what: &str,
+    ) -> Result<usize, ObjError> {
+        let token = token.unwrap_or("");
+        let index: i64 = token
+            .parse()
+            .map_err(|_| self.error(format!("invalid {} index `{}`", what, token)))?;
+
+        let resolved = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!("{} index {} out of range", what, index)));
+        }
+
+        Ok(resolved as usize)
+    }
+
+    fn select_group(&mut self, name: &str) {
+        self.current = match self.groups.iter().position(|g| g.name == name) {
+            Some(i) => i,
+            None => {
+                self.groups.push(ObjGroup {
+                    name: name.to_string(),
+                    mesh: Mesh::new(),
+                });
+                self.vertex_maps.push(HashMap::new());
+                self.groups.len() - 1
+            }
+        };
+    }
+
+    // n-gons are split as triangle fans
+    fn add_face(&mut self, corners: &[Corner]) {
+        let indices: Vec<usize> = corners.iter().map(|c| self.vertex(*c)).collect();
+        let mesh = &mut self.groups[self.current].mesh;
+
+        for i in 1..indices.len() - 1 {
+            mesh.add_triangle(indices[0], indices[i], indices[i + 1], self.color);
+        }
+    }
+
+    // Vertex of the current group for a corner, shared with the corners using
+    // the same position, texture coordinate and normal
+    fn vertex(&mut self, corner: Corner) -> usize {
+        if let Some(i) = self.vertex_maps[self.current].get(&corner) {
+            return *i;
+        }
+
+        let (position, uv, normal) = corner;
+        let mesh = &mut self.groups[self.current].mesh;
+
+        let i = mesh.add_vertex(self.positions[position].clone());
+        mesh.normals
+            .push(normal.map_or(Point3D::ZERO, |n| self.normals[n].clone()));
+        mesh.uvs.push(uv.map_or((0., 0.), |t| self.uvs[t]));
+
+        self.vertex_maps[self.current].insert(corner, i);
+        i
+    }
+}
+
+// Exactly `count` floats
+fn parse_floats<'a, I: Iterator<Item = &'a str>>(
+    tokens: I,
+    count: usize,
+) -> Result<Vec<f32>, String> {
+    let values = tokens
+        .map(|t| {
+            t.parse::<f32>()
+                .map_err(|_| format!("invalid number `{}`", t))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if values.len() != count {
+        return Err(format!("expected {} numbers, got {}", count, values.len()));
+    }
+
+    Ok(values)
+}
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+use doom::{Point3D, RenderMode, ShadingMode};
+
+use crate::{HEIGHT, WIDTH};
+
+// Largest width or height of an image, past which the framebuffer would take
+// gigabytes
+const MAX_SIZE: u32 = 16384;
+
+pub const USAGE: &str = "\
+usage: doom [options]
+
+  --level <file>       load the scene from a level file instead of the demo cube
+  --wad <file>         load a map from a Doom WAD file
+  --map <name>         map to load from the WAD, like E1M1 or MAP01 (default: the first one)
+  --painter            draw back to front from a BSP tree instead of using the depth buffer
+  --shading <mode>     flat, gouraud or phong (default: flat, L cycles through them)
+  --mip-debug          tint textures by mip level (M toggles it)
+  --falloff <distance> darken what is farther than <distance> at every pixel, like Doom
+                       (160 suits WAD maps), or 0 to turn off the level's own falloff
+  --mode <mode>        filled, wireframe, edges, normals, depth or faces (default: filled,
+                       V cycles through them)
+  --indexed            draw with 256 colours, from the WAD's palette if any (P toggles it)
+  --headless <file>    render a single frame to <file> (.ppm or .png) without opening a window
+  --export-stl <file>  write the scene to <file> as binary STL and exit
+  --size <w>x<h>       size of the headless image (default: window size)
+  --position <x,y,z>   starting position of the camera (default: the level's spawn)
+  --yaw <degrees>      starting yaw, positive turns right
+  --pitch <degrees>    starting pitch, positive looks up
+  --help               print this message";
+
+#[derive(Debug)]
+pub struct Options {
+    pub level: Option<PathBuf>,
+    pub wad: Option<PathBuf>,
+    pub map: Option<String>,
+    pub painter: bool,
+    pub shading: ShadingMode,
+    pub mip_debug: bool,
+    pub mode: RenderMode,
+    // Left to the level when not given, 0 turns it off
+    pub falloff: Option<f32>,
+    pub indexed: bool,
+    pub headless: Option<PathBuf>,
+    pub export_stl: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    // Left to the level's spawn when not given
+    pub position: Option<Point3D>,
+    pub yaw: Option<f32>,
+    pub pitch: Option<f32>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            level: None,
+            wad: None,
+            map: None,
+            painter: false,
+            shading: ShadingMode::Flat,
+            mip_debug: false,
+            mode: RenderMode::Filled,
+            falloff: None,
+            indexed: false,
+            headless: None,
+            export_stl: None,
+            width: WIDTH,
+            height: HEIGHT,
+            position: None,
+            yaw: None,
+            pitch: None,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value after {}", arg));
+
+            match arg.as_str() {
+                "--level" => options.level = Some(PathBuf::from(value()?)),
+                "--wad" => options.wad = Some(PathBuf::from(value()?)),
+                "--map" => options.map = Some(value()?.to_ascii_uppercase()),
+                "--painter" => options.painter = true,
+                "--shading" => {
+                    let v = value()?;
+                    options.shading = match v.to_ascii_lowercase().as_str() {
+                        "flat" => ShadingMode::Flat,
+                        "gouraud" => ShadingMode::Gouraud,
+                        "phong" => ShadingMode::Phong,
+                        _ => return Err(format!("invalid shading: {}", v)),
+                    };
+                }
+                "--mip-debug" => options.mip_debug = true,
+                "--mode" => {
+                    let v = value()?;
+                    options.mode = match v.to_ascii_lowercase().as_str() {
+                        "filled" => RenderMode::Filled,
+                        "wireframe" => RenderMode::Wireframe,
+                        "edges" => RenderMode::FilledEdges,
+                        "normals" => RenderMode::Normals,
+                        "depth" => RenderMode::Depth,
+                        "faces" => RenderMode::FaceId,
+                        _ => return Err(format!("invalid mode: {}", v)),
+                    };
+                }
+                "--falloff" => {
+                    let v = value()?;
+                    let distance = v
+                        .parse()
+                        .ok()
+                        .filter(|d: &f32| d.is_finite() && *d >= 0.)
+                        .ok_or(format!("invalid falloff distance: {}", v))?;
+                    options.falloff = Some(distance);
+                }
+                "--indexed" => options.indexed = true,
+                "--headless" => options.headless = Some(PathBuf::from(value()?)),
+                "--export-stl" => options.export_stl = Some(PathBuf::from(value()?)),
+                "--size" => {
+                    let v = value()?;
+                    let (w, h) = v
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|(w, h)| (1..=MAX_SIZE).contains(w) && (1..=MAX_SIZE).contains(h))
+                        .ok_or(format!("invalid size: {}", v))?;
+                    options.width = w;
+                    options.height = h;
+                }
+                "--position" => {
+                    let v = value()?;
+                    let coords: Vec<f32> = v
+                        .split(',')
+                        .map(|c| c.trim().parse().ok().filter(|c: &f32| c.is_finite()))
+                        .collect::<Option<_>>()
+                        .ok_or(format!("invalid position: {}", v))?;
+                    let [x, y, z] = coords[..] else {
+                        return Err(format!("invalid position: {}", v));
+                    };
+                    options.position = Some(Point3D::new(x, y, z));
+                }
+                "--yaw" => options.yaw = Some(parse_angle(&value()?)?),
+                "--pitch" => options.pitch = Some(parse_angle(&value()?)?),
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+
+        if options.level.is_some() && options.wad.is_some() {
+            return Err("--level and --wad cannot be used together".to_string());
+        }
+        if options.map.is_some() && options.wad.is_none() {
+            return Err("--map needs --wad".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_angle(v: &str) -> Result<f32, String> {
+    v.parse()
+        .ok()
+        .filter(|a: &f32| a.is_finite())
+        .ok_or(format!("invalid angle: {}", v))
+}
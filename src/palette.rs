@@ -0,0 +1,183 @@
+// 256-colour palettes and light levels, like Doom's PLAYPAL and COLORMAP lumps
+//
+// A colormap is a table from each palette index to the index of the same
+// colour at some light level. There are 32 of them, from full brightness to
+// almost black, so lighting a pixel is two lookups rather than arithmetic.
+// Colours that are not in the palette are first mapped to the closest one
+
+use std::fmt;
+
+use sdl2::pixels::Color;
+
+use crate::{color_mul, WadError};
+
+pub const PALETTE_SIZE: usize = 256;
+pub const LIGHT_LEVELS: usize = 32;
+
+// Bits kept of each channel by the nearest colour lookup
+const LOOKUP_BITS: u32 = 5;
+
+// Hues of the generated palette, each giving 16 shades from light to dark
+const RAMPS: [(u8, u8, u8); 16] = [
+    (255, 255, 255),
+    (255, 40, 40),
+    (255, 140, 40),
+    (255, 230, 80),
+    (200, 255, 60),
+    (60, 220, 60),
+    (40, 140, 50),
+    (60, 230, 230),
+    (80, 140, 255),
+    (40, 40, 255),
+    (170, 80, 255),
+    (255, 100, 200),
+    (255, 190, 150),
+    (200, 150, 100),
+    (140, 90, 50),
+    (150, 160, 130),
+];
+
+#[derive(Clone)]
+pub struct Palette {
+    colors: [Color; PALETTE_SIZE],
+    colormaps: Vec<[u8; PALETTE_SIZE]>,
+    // Closest index for every colour, as `LOOKUP_BITS` per channel
+    lookup: Vec<u8>,
+}
+
+impl fmt::Debug for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Palette")
+            .field("colors", &self.colors)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Palette {
+    fn eq(&self, other: &Palette) -> bool {
+        self.colors == other.colors && self.colormaps == other.colormaps
+    }
+}
+
+impl Palette {
+    // The colormaps are computed from the colours
+    pub fn new(colors: [Color; PALETTE_SIZE]) -> Palette {
+        let mut palette = Palette {
+            colors,
+            colormaps: vec![],
+            lookup: lookup(&colors),
+        };
+        palette.colormaps = (0..LIGHT_LEVELS)
+            .map(|level| {
+                let brightness = 1. - level as f32 / LIGHT_LEVELS as f32;
+                let mut colormap = [0; PALETTE_SIZE];
+                for (i, c) in colors.iter().enumerate() {
+                    colormap[i] = palette.nearest(color_mul(c, brightness));
+                }
+                colormap
+            })
+            .collect();
+        palette
+    }
+
+    // Ramps of colours going to black, with a grey one first
+    pub fn generate() -> Palette {
+        let mut colors = [Color::BLACK; PALETTE_SIZE];
+        for (r, (red, green, blue)) in RAMPS.iter().enumerate() {
+            for shade in 0..16 {
+                // Grey goes all the way from white to black
+                let brightness = if r == 0 {
+                    1. - shade as f32 / 15.
+                } else {
+                    1. - shade as f32 / 16.
+                };
+                let channel = |c: u8| (c as f32 * brightness).round() as u8;
+                colors[r * 16 + shade] = Color::RGB(channel(*red), channel(*green), channel(*blue));
+            }
+        }
+        Palette::new(colors)
+    }
+
+    // A PLAYPAL lump holds several palettes of 768 bytes, for when the player
+    // is hurt or picks something up: only the first one is used. COLORMAP
+    // lumps have 34 colormaps of 256 bytes, the last two for invulnerability
+    // and all black: only the first 32 are. Without one they are computed
+    pub fn from_lumps(playpal: &[u8], colormap: Option<&[u8]>) -> Result<Palette, WadError> {
+        if playpal.len() < PALETTE_SIZE * 3 {
+            return Err(WadError::Format("PLAYPAL too short".to_string()));
+        }
+        let mut colors = [Color::BLACK; PALETTE_SIZE];
+        for (i, rgb) in playpal.chunks_exact(3).take(PALETTE_SIZE).enumerate() {
+            colors[i] = Color::RGB(rgb[0], rgb[1], rgb[2]);
+        }
+
+        let Some(colormap) = colormap else {
+            return Ok(Palette::new(colors));
+        };
+        if colormap.len() < LIGHT_LEVELS * PALETTE_SIZE {
+            return Err(WadError::Format("COLORMAP too short".to_string()));
+        }
+
+        let colormaps = colormap
+            .chunks_exact(PALETTE_SIZE)
+            .take(LIGHT_LEVELS)
+            .map(|c| c.try_into().unwrap())
+            .collect();
+        Ok(Palette {
+            colors,
+            colormaps,
+            lookup: lookup(&colors),
+        })
+    }
+
+    pub fn color(&self, index: u8) -> Color {
+        self.colors[index as usize]
+    }
+
+    // Index of the palette colour closest to `color`
+    pub fn nearest(&self, color: Color) -> u8 {
+        let shift = 8 - LOOKUP_BITS;
+        let key = ((color.r as usize >> shift) << (2 * LOOKUP_BITS))
+            | ((color.g as usize >> shift) << LOOKUP_BITS)
+            | (color.b as usize >> shift);
+        self.lookup[key]
+    }
+
+    // `index` at `brightness`, from 0 to 1, through the closest colormap
+    pub fn light(&self, index: u8, brightness: f32) -> u8 {
+        let level = ((1. - brightness) * LIGHT_LEVELS as f32).round();
+        let level = (level.max(0.) as usize).min(LIGHT_LEVELS - 1);
+        self.colormaps[level][index as usize]
+    }
+
+    // `color` as it is shown: the closest palette colour, lit by a colormap
+    pub fn shade(&self, color: Color, brightness: f32) -> Color {
+        let mut shaded = self.color(self.light(self.nearest(color), brightness));
+        shaded.a = color.a;
+        shaded
+    }
+}
+
+// Closest colours by squared distance, each cell of the lookup taken at its centre
+fn lookup(colors: &[Color; PALETTE_SIZE]) -> Vec<u8> {
+    let cells = 1 << LOOKUP_BITS;
+    let center = |c: usize| ((c << (8 - LOOKUP_BITS)) + (1 << (7 - LOOKUP_BITS))) as i32;
+
+    let mut lookup = Vec::with_capacity(cells * cells * cells);
+    for r in 0..cells {
+        for g in 0..cells {
+            for b in 0..cells {
+                let (r, g, b) = (center(r), center(g), center(b));
+                let distance = |c: &Color| {
+                    let (dr, dg, db) = (c.r as i32 - r, c.g as i32 - g, c.b as i32 - b);
+                    dr * dr + dg * dg + db * db
+                };
+                let closest = (0..PALETTE_SIZE)
+                    .min_by_key(|i| distance(&colors[*i]))
+                    .unwrap();
+                lookup.push(closest as u8);
+            }
+        }
+    }
+    lookup
+}
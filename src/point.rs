@@ -1,4 +1,4 @@
-use crate::{Rotation3, FOV, PLAYER, ROTATION, WIDTH, HEIGHT};
+use crate::{Quaternion, Rotation3};
 use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 
 #[derive(Debug, Clone)]
@@ -40,6 +40,10 @@ impl Point3D {
             .rotate_z(center, rot.rot_z)
     }
 
+    pub fn rotate_by(&self, q: &Quaternion) -> Point3D {
+        q.rotate(self)
+    }
+
     pub fn rotate_x(&self, center: &Point3D, angle_rad: f32) -> Point3D {
         let s = angle_rad.sin();
         let c = angle_rad.cos();
@@ -219,65 +223,3 @@ impl SubAssign for Point3D {
         self.z -= rhs.z;
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct Point2D {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl Point2D {
-    pub fn new(x: i32, y: i32) -> Point2D {
-        Point2D { x, y }
-    }
-}
-
-impl From<&Point3D> for Point2D {
-    fn from(value: &Point3D) -> Point2D {
-        let player = {
-            let p = PLAYER.lock().unwrap();
-            p.clone()
-        };
-
-        let theta = {
-            let t = ROTATION.lock().unwrap();
-            t.clone()
-        };
-
-	// Get object coordinate regarding player's position
-	let value = value - &player;
-	
-	// Rotate around Y
-	let value = value.rotate_y(&Point3D::ZERO, theta.rot_y);
-	
-	// Rotate around X
-	let value = value.rotate_x(&Point3D::ZERO, theta.rot_x);
-
-	// Projection
-        if value.z <= 0.0 {
-            return Point2D { x: -1, y: -1 }; // Ignore if behind camera
-        }
-
-        let d = WIDTH as f32 / (2.0 * (FOV / 2.0).tan());
-
-        let screen_x = (value.x / value.z) * d + WIDTH as f32 / 2.0;
-        let screen_y = (value.y / value.z) * d + HEIGHT as f32 / 2.0;
-
-        Point2D {
-            x: screen_x.round() as i32,
-            y: screen_y.round() as i32,
-        }
-    }
-}
-
-impl From<Point3D> for Point2D {
-    fn from(value: Point3D) -> Point2D {
-        Point2D::from(&value)
-    }
-}
-
-impl From<&Point2D> for sdl2::rect::Point {
-    fn from(value: &Point2D) -> sdl2::rect::Point {
-        sdl2::rect::Point::new(value.x, value.y)
-    }
-}
@@ -0,0 +1,134 @@
+use crate::{Point3D, Rotation3};
+use std::ops::{Mul, MulAssign};
+
+#[derive(Debug, Clone)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.,
+        x: 0.,
+        y: 0.,
+        z: 0.,
+    };
+
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    // Rotation of `angle` radians around `axis` (the axis does not need to be normalized)
+    pub fn from_axis_angle(axis: &Point3D, angle: f32) -> Quaternion {
+        let axis = axis.normalize();
+        let half = angle / 2.;
+        let s = half.sin();
+
+        Quaternion::new(half.cos(), axis.x * s, axis.y * s, axis.z * s)
+    }
+
+    // Same convention as `Point3D::rotate`: X first, then Y, then Z
+    pub fn from_euler(rot: &Rotation3) -> Quaternion {
+        let qx = Quaternion::from_axis_angle(&Point3D::X, rot.rot_x);
+        let qy = Quaternion::from_axis_angle(&Point3D::Y, rot.rot_y);
+        let qz = Quaternion::from_axis_angle(&Point3D::Z, rot.rot_z);
+
+        qz * qy * qx
+    }
+
+    pub fn to_euler(&self) -> Rotation3 {
+        let q = self.normalize();
+
+        let rot_x = (2. * (q.w * q.x + q.y * q.z)).atan2(1. - 2. * (q.x * q.x + q.y * q.y));
+        let rot_y = (2. * (q.w * q.y - q.z * q.x)).clamp(-1., 1.).asin();
+        let rot_z = (2. * (q.w * q.z + q.x * q.y)).atan2(1. - 2. * (q.y * q.y + q.z * q.z));
+
+        Rotation3::new(rot_x, rot_y, rot_z)
+    }
+
+    // Returns the (normalized) rotation axis and the angle in radians
+    pub fn to_axis_angle(&self) -> (Point3D, f32) {
+        let q = self.normalize();
+        let angle = 2. * q.w.clamp(-1., 1.).acos();
+        let s = (1. - q.w * q.w).max(0.).sqrt();
+
+        if s < 1e-6 {
+            return (Point3D::X, 0.);
+        }
+
+        (Point3D::new(q.x / s, q.y / s, q.z / s), angle)
+    }
+
+    pub fn norm(&self) -> f32 {
+        (self.w.powi(2) + self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        let n = self.norm();
+        if n == 0. {
+            return Quaternion::IDENTITY;
+        }
+
+        Quaternion::new(self.w / n, self.x / n, self.y / n, self.z / n)
+    }
+
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    // The zero quaternion has no inverse: it gives the identity, like `normalize`
+    pub fn inverse(&self) -> Quaternion {
+        let n2 = self.w.powi(2) + self.x.powi(2) + self.y.powi(2) + self.z.powi(2);
+        if n2 == 0. {
+            return Quaternion::IDENTITY;
+        }
+        let c = self.conjugate();
+
+        Quaternion::new(c.w / n2, c.x / n2, c.y / n2, c.z / n2)
+    }
+
+    pub fn rotate(&self, v: &Point3D) -> Point3D {
+        // v' = q v q*, expanded to avoid building the intermediate quaternions
+        let u = Point3D::new(self.x, self.y, self.z);
+        let t = (u.clone() * v.clone()) * 2.;
+
+        v + &(&t * self.w) + (u * t)
+    }
+}
+
+impl Mul for &Quaternion {
+    type Output = Quaternion;
+
+    // Hamilton product: `a * b` applies `b` first, then `a`
+    fn mul(self, rhs: &Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        &self * &rhs
+    }
+}
+
+impl MulAssign for Quaternion {
+    fn mul_assign(&mut self, rhs: Quaternion) {
+        *self = &*self * &rhs
+    }
+}
+
+impl From<&Rotation3> for Quaternion {
+    fn from(value: &Rotation3) -> Quaternion {
+        Quaternion::from_euler(value)
+    }
+}
@@ -0,0 +1,773 @@
+use std::rc::Rc;
+
+use sdl2::pixels::Color;
+
+use crate::{
+    BspTree, Camera, Fog, Framebuffer, Interpolate, Light, LightFalloff, Material, Mesh, Palette,
+    Point3D, ScreenPoint, TexCoord,
+};
+
+pub fn color_mul(color: &Color, factor: f32) -> Color {
+    let r = (color.r as f32 * factor).round().clamp(0.0, 255.0) as u8;
+    let g = (color.g as f32 * factor).round().clamp(0.0, 255.0) as u8;
+    let b = (color.b as f32 * factor).round().clamp(0.0, 255.0) as u8;
+    let a = color.a;
+
+    Color { r, g, b, a }
+}
+
+// Lines of `RenderMode::FilledEdges`
+const EDGE_COLOR: Color = Color::BLACK;
+
+// Tints of `Renderer::mip_debug`, the last one for every smaller level
+const MIP_COLORS: [Color; 6] = [
+    Color::RED,
+    Color::YELLOW,
+    Color::GREEN,
+    Color::CYAN,
+    Color::BLUE,
+    Color::MAGENTA,
+];
+
+// Where the lighting is computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingMode {
+    // Once per face, at its centre
+    #[default]
+    Flat,
+    // At the corners, from the vertex normals, with the colours blended in between
+    Gouraud,
+    // At every pixel, from the vertex normals blended in between
+    Phong,
+}
+
+impl ShadingMode {
+    // The following mode, back to Flat after Phong
+    pub fn next(self) -> ShadingMode {
+        match self {
+            ShadingMode::Flat => ShadingMode::Gouraud,
+            ShadingMode::Gouraud => ShadingMode::Phong,
+            ShadingMode::Phong => ShadingMode::Flat,
+        }
+    }
+}
+
+// What is drawn of each face
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    #[default]
+    Filled,
+    // Only the edges, in the colour of the face
+    Wireframe,
+    // Filled, with the edges drawn over
+    FilledEdges,
+    // Filled, with the normals as lines coloured by their direction: the
+    // faces' with flat shading, the vertices' otherwise
+    Normals,
+    // Greyscale, the closest white, over the range of depths in view
+    Depth,
+    // Each face in a colour of its own
+    FaceId,
+}
+
+impl RenderMode {
+    // The following mode, back to Filled after FaceId
+    pub fn next(self) -> RenderMode {
+        match self {
+            RenderMode::Filled => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::FilledEdges,
+            RenderMode::FilledEdges => RenderMode::Normals,
+            RenderMode::Normals => RenderMode::Depth,
+            RenderMode::Depth => RenderMode::FaceId,
+            RenderMode::FaceId => RenderMode::Filled,
+        }
+    }
+}
+
+pub struct Renderer {
+    pub background: Color,
+    pub lights: Vec<Light>,
+    // Light reaching every face from everywhere, on top of the lights
+    pub ambient: f32,
+    pub shading: ShadingMode,
+    pub mode: RenderMode,
+    pub fog: Option<Fog>,
+    pub falloff: Option<LightFalloff>,
+    // Indexed colour: faces are lit through the palette's colormaps, which
+    // only darken, in grey, and the frame is then made of palette colours
+    pub palette: Option<Rc<Palette>>,
+    // Tints textures by the mip level they are sampled from: red for the
+    // full size image, then yellow, green, cyan, blue and magenta
+    pub mip_debug: bool,
+}
+
+impl Renderer {
+    // Without lights and with full ambient light: faces get their own colour
+    pub fn new(background: Color) -> Renderer {
+        Renderer {
+            background,
+            lights: vec![],
+            ambient: 1.,
+            shading: ShadingMode::Flat,
+            mode: RenderMode::Filled,
+            fog: None,
+            falloff: None,
+            palette: None,
+            mip_debug: false,
+        }
+    }
+
+    // Colour of `material` at `point` of a surface facing `normal`
+    // (normalized), seen from `eye`
+    pub fn shade(
+        &self,
+        material: &Material,
+        point: &Point3D,
+        normal: &Point3D,
+        eye: &Point3D,
+    ) -> Color {
+        self.lit(
+            &material.color,
+            &self.lighting(material, point, normal, eye),
+            1.,
+        )
+    }
+
+    // `color` under `light`, as given by `lighting`, darkened by `falloff`
+    fn lit(&self, color: &Color, light: &[f32; 6], falloff: f32) -> Color {
+        match &self.palette {
+            // Coloured lights and highlights only count by their brightness
+            Some(palette) => {
+                let brightness = light.iter().sum::<f32>() / 3.;
+                palette.shade(*color, brightness * falloff)
+            }
+            None => color_mul(&apply_light(color, light), falloff),
+        }
+    }
+
+    // Ambient plus Lambert diffuse light from every light, then the Blinn-Phong
+    // highlights, each for red, green and blue
+    fn lighting(
+        &self,
+        material: &Material,
+        point: &Point3D,
+        normal: &Point3D,
+        eye: &Point3D,
+    ) -> [f32; 6] {
+        let mut light = [self.ambient, self.ambient, self.ambient, 0., 0., 0.];
+        for l in self.lights.iter() {
+            let diffuse = l.diffuse(point, normal);
+            let specular = if material.specular > 0. {
+                l.specular(point, normal, eye, material.shininess)
+            } else {
+                [0.; 3]
+            };
+            for i in 0..3 {
+                light[i] += diffuse[i];
+                light[i + 3] += specular[i] * material.specular;
+            }
+        }
+        light
+    }
+
+    // Colour of `material` at `coord`, before lighting
+    fn texture_color(&self, material: &Material, coord: &TexCoord) -> Color {
+        let color = material.color_at(coord);
+        match &material.texture {
+            Some(texture) if self.mip_debug => {
+                let tint =
+                    MIP_COLORS[(texture.lod(coord).round() as usize).min(MIP_COLORS.len() - 1)];
+                let half = |c: u8, t: u8| ((c as u16 + t as u16) / 2) as u8;
+                Color::RGBA(
+                    half(color.r, tint.r),
+                    half(color.g, tint.g),
+                    half(color.b, tint.b),
+                    color.a,
+                )
+            }
+            _ => color,
+        }
+    }
+
+    // Fresh framebuffer the size of the camera's viewport, for headless rendering
+    pub fn render_to_framebuffer(&self, camera: &Camera, meshes: &[Mesh]) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(camera.width, camera.height);
+        self.render(camera, meshes, &mut framebuffer);
+        framebuffer
+    }
+
+    // Occlusion is resolved per pixel by the depth buffer, so faces can come in
+    // any order. With the framebuffer's depth test off, they must come back to
+    // front, like from `render_bsp`
+    pub fn render(&self, camera: &Camera, meshes: &[Mesh], framebuffer: &mut Framebuffer) {
+        framebuffer.clear(self.background);
+
+        for pass in self.passes(framebuffer) {
+            for_each_face(meshes, |face| {
+                self.draw_face(camera, face, pass, framebuffer)
+            });
+        }
+
+        self.finish(framebuffer);
+    }
+
+    // Painter's algorithm: the tree gives the faces farthest first, so the
+    // depth test can be turned off
+    pub fn render_bsp(&self, camera: &Camera, tree: &BspTree, framebuffer: &mut Framebuffer) {
+        framebuffer.clear(self.background);
+
+        let polygons = tree.back_to_front(camera.position());
+        for pass in self.passes(framebuffer) {
+            for polygon in polygons.iter() {
+                let face = FaceToDraw {
+                    vertices: &polygon.vertices,
+                    normal: &polygon.normal,
+                    normals: &polygon.normals,
+                    uvs: &polygon.uvs,
+                    material: &polygon.material,
+                    id: polygon.id,
+                };
+                self.draw_face(camera, &face, pass, framebuffer);
+            }
+        }
+
+        self.finish(framebuffer);
+    }
+
+    // With the depth test, lines come once every face is filled: a face drawn
+    // after its neighbour would cover their common edge otherwise. Without it,
+    // each face's lines must come right after it, before what is in front
+    fn passes(&self, framebuffer: &Framebuffer) -> &'static [Pass] {
+        let lines = matches!(self.mode, RenderMode::FilledEdges | RenderMode::Normals);
+        if lines && framebuffer.depth_test() {
+            &[Pass::Fill, Pass::Lines]
+        } else {
+            &[Pass::All]
+        }
+    }
+
+    // What is done with the whole frame once every face is drawn
+    fn finish(&self, framebuffer: &mut Framebuffer) {
+        if self.mode == RenderMode::Depth {
+            show_depth(framebuffer);
+        }
+
+        // Fog, lines and the background may not be palette colours yet
+        if let Some(palette) = &self.palette {
+            for y in 0..framebuffer.height() {
+                for x in 0..framebuffer.width() {
+                    let color = framebuffer.get_pixel(x, y);
+                    framebuffer.set_pixel(x, y, palette.color(palette.nearest(color)));
+                }
+            }
+        }
+    }
+
+    fn draw_face(
+        &self,
+        camera: &Camera,
+        face: &FaceToDraw,
+        pass: &Pass,
+        framebuffer: &mut Framebuffer,
+    ) {
+        let FaceToDraw {
+            vertices,
+            normal,
+            material,
+            ..
+        } = face;
+
+        let Some(side) = visible_side(camera.position(), vertices, normal, material.two_sided)
+        else {
+            return;
+        };
+
+        let (fill, lines) = (*pass != Pass::Lines, *pass != Pass::Fill);
+        match self.mode {
+            RenderMode::Filled => self.fill_face(camera, face, side, framebuffer),
+            RenderMode::Wireframe => self.draw_edges(camera, vertices, material.color, framebuffer),
+            RenderMode::FilledEdges => {
+                if fill {
+                    self.fill_face(camera, face, side, framebuffer);
+                }
+                if lines {
+                    self.draw_edges(camera, vertices, EDGE_COLOR, framebuffer);
+                }
+            }
+            RenderMode::Normals => {
+                if fill {
+                    self.fill_face(camera, face, side, framebuffer);
+                }
+                if lines {
+                    self.draw_normals(camera, face, side, framebuffer);
+                }
+            }
+            // Recoloured by `finish`
+            RenderMode::Depth => {
+                if let Some(projected) = project(camera, &camera.clip(vertices)) {
+                    framebuffer.fill_polygon(&projected, Color::WHITE);
+                }
+            }
+            RenderMode::FaceId => {
+                if let Some(projected) = project(camera, &camera.clip(vertices)) {
+                    framebuffer.fill_polygon(&projected, face_color(face.id));
+                }
+            }
+        }
+    }
+
+    fn draw_edges(
+        &self,
+        camera: &Camera,
+        vertices: &[Point3D],
+        color: Color,
+        framebuffer: &mut Framebuffer,
+    ) {
+        for (i, a) in vertices.iter().enumerate() {
+            let b = &vertices[(i + 1) % vertices.len()];
+            draw_segment(camera, a, b, color, framebuffer);
+        }
+    }
+
+    // From the centre of the face, or from its corners, about half as long as
+    // the face is wide
+    fn draw_normals(
+        &self,
+        camera: &Camera,
+        face: &FaceToDraw,
+        side: f32,
+        framebuffer: &mut Framebuffer,
+    ) {
+        let vertices = face.vertices;
+        let center =
+            &vertices.iter().fold(Point3D::ZERO, |sum, v| &sum + v) * (1. / vertices.len() as f32);
+        let length = vertices.iter().map(|v| (v - &center).norm()).sum::<f32>()
+            / vertices.len() as f32
+            * 0.5;
+
+        let mut draw = |from: &Point3D, normal: &Point3D| {
+            if normal.norm() == 0. {
+                return;
+            }
+            let normal = &normal.normalize() * side;
+            let channel = |c: f32| ((c + 1.) * 127.5).round() as u8;
+            let color = Color::RGB(channel(normal.x), channel(normal.y), channel(normal.z));
+            draw_segment(
+                camera,
+                from,
+                &(from + &(&normal * length)),
+                color,
+                framebuffer,
+            );
+        };
+
+        if self.shading == ShadingMode::Flat {
+            draw(&center, face.normal);
+        } else {
+            for (v, n) in vertices.iter().zip(face.normals) {
+                draw(v, n);
+            }
+        }
+    }
+
+    fn fill_face(
+        &self,
+        camera: &Camera,
+        face: &FaceToDraw,
+        side: f32,
+        framebuffer: &mut Framebuffer,
+    ) {
+        let FaceToDraw {
+            vertices,
+            normal,
+            normals,
+            material,
+            ..
+        } = face;
+        let eye = camera.position();
+
+        // Texture coordinates go along with whatever else is interpolated
+        let textured = material.texture.is_some() && face.uvs.len() == vertices.len();
+        let uv = |i: usize| if textured { face.uvs[i] } else { (0., 0.) };
+
+        let center =
+            &vertices.iter().fold(Point3D::ZERO, |sum, v| &sum + v) * (1. / vertices.len() as f32);
+        // A large face, like a floor, can have its centre behind the camera
+        // while the rest stretches far away: its fog and falloff then come from
+        // the depth of each pixel
+        let face_depth = Some(camera.to_view(&center).z).filter(|d| *d >= camera.near);
+
+        // Only keep what is in front of the camera, which may add or remove corners
+        match self.shading {
+            ShadingMode::Flat => {
+                let light = self.lighting(material, &center, &(&normal.normalize() * side), eye);
+
+                if let (false, false, Some(face_depth)) =
+                    (textured, self.per_pixel_distance(), face_depth)
+                {
+                    let Some(projected) = project(camera, &camera.clip(vertices)) else {
+                        return;
+                    };
+                    let color = self.at_distance(&material.color, &light, face_depth, face_depth);
+                    framebuffer.fill_polygon(&projected, color);
+                    return;
+                }
+
+                let uvs: Vec<(f32, f32)> = (0..vertices.len()).map(uv).collect();
+                let (clipped, uvs) = camera.clip_with(vertices, &uvs);
+                let Some(projected) = project(camera, &clipped) else {
+                    return;
+                };
+                let attributes: Vec<((), (f32, f32))> =
+                    uvs.into_iter().map(|uv| ((), uv)).collect();
+                fill_interpolated(framebuffer, &projected, &attributes, |_, coord, depth| {
+                    let color = self.texture_color(material, coord);
+                    self.at_distance(&color, &light, face_depth.unwrap_or(depth), depth)
+                });
+            }
+            ShadingMode::Gouraud => {
+                let attributes: Vec<([f32; 6], (f32, f32))> = vertices
+                    .iter()
+                    .zip(normals.iter())
+                    .enumerate()
+                    .map(|(i, (v, n))| (self.lighting(material, v, &(n * side), eye), uv(i)))
+                    .collect();
+
+                let (clipped, attributes) = camera.clip_with(vertices, &attributes);
+                let Some(projected) = project(camera, &clipped) else {
+                    return;
+                };
+                fill_interpolated(
+                    framebuffer,
+                    &projected,
+                    &attributes,
+                    |light, coord, depth| {
+                        let color = self.texture_color(material, coord);
+                        self.at_distance(&color, light, face_depth.unwrap_or(depth), depth)
+                    },
+                );
+            }
+            ShadingMode::Phong => {
+                let attributes: Vec<((Point3D, Point3D), (f32, f32))> = vertices
+                    .iter()
+                    .zip(normals.iter())
+                    .enumerate()
+                    .map(|(i, (v, n))| ((v.clone(), n * side), uv(i)))
+                    .collect();
+
+                let (clipped, attributes) = camera.clip_with(vertices, &attributes);
+                let Some(projected) = project(camera, &clipped) else {
+                    return;
+                };
+                fill_interpolated(
+                    framebuffer,
+                    &projected,
+                    &attributes,
+                    |(point, normal), coord, depth| {
+                        let normal = if normal.norm() > 0. {
+                            normal.normalize()
+                        } else {
+                            normal.clone()
+                        };
+                        let light = self.lighting(material, point, &normal, eye);
+                        let color = self.texture_color(material, coord);
+                        self.at_distance(&color, &light, face_depth.unwrap_or(depth), depth)
+                    },
+                );
+            }
+        }
+    }
+
+    // Lighting with the falloff, then fog, from the depth of the face or of
+    // the pixel depending on how each is set up
+    fn at_distance(
+        &self,
+        color: &Color,
+        light: &[f32; 6],
+        face_depth: f32,
+        pixel_depth: f32,
+    ) -> Color {
+        let falloff = self.falloff.as_ref().map_or(1., |falloff| {
+            falloff.factor(if falloff.per_pixel {
+                pixel_depth
+            } else {
+                face_depth
+            })
+        });
+        let mut color = self.lit(color, light, falloff);
+        if let Some(fog) = &self.fog {
+            let depth = if fog.per_pixel {
+                pixel_depth
+            } else {
+                face_depth
+            };
+            color = fog.apply(color, depth);
+        }
+        color
+    }
+
+    fn per_pixel_distance(&self) -> bool {
+        self.fog.as_ref().is_some_and(|f| f.per_pixel)
+            || self.falloff.as_ref().is_some_and(|f| f.per_pixel)
+    }
+}
+
+// What `Renderer::draw_face` needs of a face. `normal` is the face's, `normals`
+// and `uvs` the ones of its vertices, `uvs` being empty without a texture
+struct FaceToDraw<'a> {
+    vertices: &'a [Point3D],
+    normal: &'a Point3D,
+    normals: &'a [Point3D],
+    uvs: &'a [(f32, f32)],
+    material: &'a Material,
+    // For `RenderMode::FaceId`
+    id: usize,
+}
+
+// Part of the drawing of faces, see `Renderer::passes`
+#[derive(PartialEq)]
+enum Pass {
+    Fill,
+    Lines,
+    All,
+}
+
+// Every face of the meshes, numbered in order
+fn for_each_face<F: FnMut(&FaceToDraw)>(meshes: &[Mesh], mut draw: F) {
+    let mut id = 0;
+    for mesh in meshes.iter() {
+        let vertex_normals = mesh.vertex_normals();
+        for face in mesh.faces.iter() {
+            let normal = mesh.face_normal(face);
+            let normals: Vec<Point3D> = face
+                .indices
+                .iter()
+                .map(|i| vertex_normals[*i].clone())
+                .collect();
+            let uvs: Vec<(f32, f32)> = if mesh.has_uvs() {
+                face.indices.iter().map(|i| mesh.uvs[*i]).collect()
+            } else {
+                vec![]
+            };
+            let vertices = mesh.face_vertices(face);
+            draw(&FaceToDraw {
+                vertices: &vertices,
+                normal: &normal,
+                normals: &normals,
+                uvs: &uvs,
+                material: &face.material,
+                id,
+            });
+            id += 1;
+        }
+    }
+}
+
+// 1 when the front of the face is seen from `eye`, -1 for the back of a
+// two-sided face, None when it is culled. Which side is seen depends on where
+// the face is from the camera, not on where the camera looks: near the screen
+// edges the two differ
+fn visible_side(
+    eye: &Point3D,
+    vertices: &[Point3D],
+    normal: &Point3D,
+    two_sided: bool,
+) -> Option<f32> {
+    if normal.dot(&(&vertices[0] - eye)) < 0. {
+        Some(1.)
+    } else if two_sided {
+        Some(-1.)
+    } else {
+        None
+    }
+}
+
+fn draw_segment(
+    camera: &Camera,
+    a: &Point3D,
+    b: &Point3D,
+    color: Color,
+    framebuffer: &mut Framebuffer,
+) {
+    let Some((a, b)) = camera.clip_segment(a, b) else {
+        return;
+    };
+    if let (Some(a), Some(b)) = (camera.project(&a), camera.project(&b)) {
+        framebuffer.draw_line(&a, &b, color);
+    }
+}
+
+// Depths as shades of grey, lighter when closer
+fn show_depth(framebuffer: &mut Framebuffer) {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    let pixels = || (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)));
+    let depths = pixels()
+        .map(|(x, y)| framebuffer.depth_at(x, y))
+        .filter(|d| d.is_finite());
+    let (near, far) = depths.fold((f32::INFINITY, 0f32), |(near, far), d| {
+        (near.min(d), far.max(d))
+    });
+
+    for (x, y) in pixels() {
+        let depth = framebuffer.depth_at(x, y);
+        if !depth.is_finite() {
+            continue;
+        }
+        // The farthest is kept apart from a black background
+        let t = if far > near {
+            (depth - near) / (far - near)
+        } else {
+            0.
+        };
+        let grey = (255. - t * 223.).round() as u8;
+        framebuffer.set_pixel(x, y, Color::RGB(grey, grey, grey));
+    }
+}
+
+// Scattered so that neighbouring faces differ, never too dark
+fn face_color(id: usize) -> Color {
+    let hash = (id as u32).wrapping_add(1).wrapping_mul(0x9e37_79b1);
+    Color::RGB(
+        (hash >> 24) as u8 | 0x40,
+        (hash >> 16) as u8 | 0x40,
+        (hash >> 8) as u8 | 0x40,
+    )
+}
+
+// None when nothing is left to draw
+fn project(camera: &Camera, vertices: &[Point3D]) -> Option<Vec<ScreenPoint>> {
+    let projected: Option<Vec<ScreenPoint>> = vertices.iter().map(|v| camera.project(v)).collect();
+    projected.filter(|p| p.len() >= 3)
+}
+
+// `light` as given by `Renderer::lighting`
+fn apply_light(color: &Color, light: &[f32; 6]) -> Color {
+    let channel = |c: u8, i: usize| {
+        (c as f32 * light[i] + 255. * light[i + 3])
+            .round()
+            .clamp(0., 255.) as u8
+    };
+    Color::RGBA(
+        channel(color.r, 0),
+        channel(color.g, 1),
+        channel(color.b, 2),
+        color.a,
+    )
+}
+
+// Same as `Framebuffer::fill_polygon`, with the colour of each pixel computed
+// from the corners' attributes and texture coordinates, blended
+// perspective-correctly, and from its depth
+fn fill_interpolated<T, F>(
+    framebuffer: &mut Framebuffer,
+    points: &[ScreenPoint],
+    attributes: &[(T, (f32, f32))],
+    mut color: F,
+) where
+    T: Interpolate,
+    F: FnMut(&T, &TexCoord, f32) -> Color,
+{
+    for i in 1..points.len().saturating_sub(1) {
+        let [a, b, c] = [&attributes[0], &attributes[i], &attributes[i + 1]];
+        let values = [&a.0, &b.0, &c.0];
+        let uvs = [&a.1, &b.1, &c.1];
+
+        framebuffer.fill_triangle([&points[0], &points[i], &points[i + 1]], |fragment| {
+            let (u, v) = <(f32, f32)>::blend(uvs, fragment.weights);
+            let change = |weights: [f32; 3]| <(f32, f32)>::blend(uvs, weights);
+            let coord = TexCoord {
+                u,
+                v,
+                dx: change(fragment.weights_dx),
+                dy: change(fragment.weights_dy),
+            };
+            color(&T::blend(values, fragment.weights), &coord, fragment.depth)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quaternion;
+
+    const SIZE: u32 = 64;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Point3D::ZERO,
+            Quaternion::IDENTITY,
+            90f32.to_radians(),
+            SIZE,
+            SIZE,
+        )
+    }
+
+    // Square on the right of the view, centred on (20, 0, 30), facing the
+    // camera but turned a little away from its view axis: its normal is along
+    // (-1, 0, 0.5)
+    fn edge_square() -> Vec<Point3D> {
+        let center = Point3D::new(20., 0., 30.);
+        let (a, b) = (Point3D::new(2., 0., 4.), Point3D::new(0., 4., 0.));
+        vec![
+            &(&center - &a) - &b,
+            &(&center + &a) - &b,
+            &(&center + &a) + &b,
+            &(&center - &a) + &b,
+        ]
+    }
+
+    fn mesh(vertices: Vec<Point3D>, material: Material) -> Mesh {
+        let mut mesh = Mesh::new();
+        let indices: Vec<usize> = vertices.into_iter().map(|v| mesh.add_vertex(v)).collect();
+        mesh.add_face(&indices, material);
+        mesh
+    }
+
+    // Colour where the centre of the square lands
+    fn center_pixel(mesh: Mesh) -> Color {
+        let camera = camera();
+        let framebuffer = Renderer::new(Color::BLACK).render_to_framebuffer(&camera, &[mesh]);
+        let center = camera.project(&Point3D::new(20., 0., 30.)).unwrap();
+        framebuffer.get_pixel(center.x as u32, center.y as u32)
+    }
+
+    #[test]
+    fn faces_near_the_screen_edge_are_kept() {
+        let camera = camera();
+        let vertices = edge_square();
+        let square = mesh(vertices.clone(), Material::new(Color::WHITE));
+        let normal = square.face_normal(&square.faces[0]);
+
+        // Looking at the view direction alone, the face would be seen from behind
+        assert!(normal.dot(&camera.forward()) > 0.);
+        assert_eq!(
+            visible_side(camera.position(), &vertices, &normal, false),
+            Some(1.)
+        );
+        assert_eq!(center_pixel(square), Color::WHITE);
+    }
+
+    #[test]
+    fn back_faces_are_culled_unless_two_sided() {
+        let camera = camera();
+        let mut vertices = edge_square();
+        vertices.reverse();
+        let square = mesh(vertices.clone(), Material::new(Color::WHITE));
+        let normal = square.face_normal(&square.faces[0]);
+
+        assert_eq!(
+            visible_side(camera.position(), &vertices, &normal, false),
+            None
+        );
+        assert_eq!(
+            visible_side(camera.position(), &vertices, &normal, true),
+            Some(-1.)
+        );
+        assert_eq!(center_pixel(square), Color::BLACK);
+        assert_eq!(
+            center_pixel(mesh(vertices, Material::two_sided(Color::WHITE))),
+            Color::WHITE
+        );
+    }
+}
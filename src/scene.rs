@@ -0,0 +1,148 @@
+use crate::{Mat4, Mesh, Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+// The transform is relative to the parent node, and the mesh is in the node's
+// own space
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub transform: Transform,
+    pub mesh: Option<Mesh>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Mat4,
+}
+
+impl Node {
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    // As of the last `update_world_transforms`
+    pub fn world_transform(&self) -> &Mat4 {
+        &self.world
+    }
+}
+
+// Nodes are never removed, so a `NodeId` stays valid for the lifetime of its graph
+#[derive(Debug, Clone, Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph::default()
+    }
+
+    pub fn add_node(
+        &mut self,
+        name: &str,
+        parent: Option<NodeId>,
+        transform: Transform,
+        mesh: Option<Mesh>,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+
+        self.nodes.push(Node {
+            name: name.to_string(),
+            transform,
+            mesh,
+            parent,
+            children: vec![],
+            world: Mat4::IDENTITY,
+        });
+
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+
+        id
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes.iter().enumerate().map(|(i, n)| (NodeId(i), n))
+    }
+
+    // First node with that name
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|n| n.name == name).map(NodeId)
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes()
+            .filter(|(_, n)| n.parent.is_none())
+            .map(|(id, _)| id)
+    }
+
+    // Moves a node (with its children) under another one, or makes it a root.
+    // Returns false, leaving the graph untouched, if that would create a cycle
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == id {
+                return false;
+            }
+            ancestor = self.nodes[a.0].parent;
+        }
+
+        if let Some(old) = self.nodes[id.0].parent {
+            self.nodes[old.0].children.retain(|c| *c != id);
+        }
+        if let Some(new) = parent {
+            self.nodes[new.0].children.push(id);
+        }
+        self.nodes[id.0].parent = parent;
+
+        true
+    }
+
+    // To call once per frame, after moving nodes and before drawing them.
+    // Returns whether any node moved, for `world_meshes` to be rebuilt only then
+    pub fn update_world_transforms(&mut self) -> bool {
+        let mut stack: Vec<(NodeId, Mat4)> = self.roots().map(|id| (id, Mat4::IDENTITY)).collect();
+        let mut changed = false;
+
+        while let Some((id, parent_world)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            let world = &parent_world * &node.transform.matrix();
+            if world != node.world {
+                node.world = world;
+                changed = true;
+            }
+
+            for child in node.children.iter() {
+                stack.push((*child, node.world.clone()));
+            }
+        }
+
+        changed
+    }
+
+    // Meshes of every node, moved to world space, with their vertex normals
+    // computed once rather than every time they are drawn
+    pub fn world_meshes(&self) -> Vec<Mesh> {
+        self.nodes
+            .iter()
+            .filter_map(|n| {
+                let mut mesh = n.mesh.as_ref()?.transformed(&n.world);
+                mesh.compute_normals();
+                Some(mesh)
+            })
+            .collect()
+    }
+}
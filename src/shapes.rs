@@ -0,0 +1,311 @@
+// Parametric shape generators
+//
+// "Up" is -Y, like for `Cube`. Faces are wound like the cube's, so that
+// `Mesh::face_normal` points outwards and the back-face test keeps the visible
+// side. Tessellation counts below the minimum making sense are raised to it.
+// Curved surfaces are made of quads where those are planar and triangles
+// elsewhere, and flat caps are single n-gons with their own vertices
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use sdl2::pixels::Color;
+
+use crate::{Mesh, Point3D};
+
+pub fn uv_sphere(
+    center: &Point3D,
+    radius: f32,
+    segments: usize,
+    rings: usize,
+    color: Color,
+) -> Mesh {
+    let segments = segments.max(3);
+    let rings = rings.max(2);
+
+    let mut mesh = Mesh::new();
+    let top = mesh.add_vertex(center + &Point3D::new(0., -radius, 0.));
+
+    // Inner rings, from top to bottom
+    let ring_indices: Vec<Vec<usize>> = (1..rings)
+        .map(|i| {
+            let theta = PI * i as f32 / rings as f32;
+            let y = -radius * theta.cos();
+            ring(center, radius * theta.sin(), y, segments)
+                .into_iter()
+                .map(|p| mesh.add_vertex(p))
+                .collect()
+        })
+        .collect();
+
+    let bottom = mesh.add_vertex(center + &Point3D::new(0., radius, 0.));
+
+    for j in 0..segments {
+        let k = (j + 1) % segments;
+
+        mesh.add_triangle(top, ring_indices[0][j], ring_indices[0][k], color);
+
+        for band in ring_indices.windows(2) {
+            let (upper, lower) = (&band[0], &band[1]);
+            mesh.add_face(&[upper[j], lower[j], lower[k], upper[k]], color);
+        }
+
+        let last = &ring_indices[rings - 2];
+        mesh.add_triangle(last[j], bottom, last[k], color);
+    }
+
+    mesh
+}
+
+// Subdivided icosahedron: triangles of nearly equal size, unlike the UV sphere
+pub fn icosphere(center: &Point3D, radius: f32, subdivisions: usize, color: Color) -> Mesh {
+    let t = (1. + 5f32.sqrt()) / 2.;
+
+    let mut vertices: Vec<Point3D> = [
+        (-1., t, 0.),
+        (1., t, 0.),
+        (-1., -t, 0.),
+        (1., -t, 0.),
+        (0., -1., t),
+        (0., 1., t),
+        (0., -1., -t),
+        (0., 1., -t),
+        (t, 0., -1.),
+        (t, 0., 1.),
+        (-t, 0., -1.),
+        (-t, 0., 1.),
+    ]
+    .iter()
+    .map(|(x, y, z)| Point3D::new(*x, *y, *z).normalize())
+    .collect();
+
+    let mut triangles: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Middle of each edge, shared by the two triangles on both sides
+        let mut middles: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut middle = |a: usize, b: usize, vertices: &mut Vec<Point3D>| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let m = &vertices[a] + &vertices[b];
+                vertices.push(m.normalize());
+                vertices.len() - 1
+            })
+        };
+
+        triangles = triangles
+            .iter()
+            .flat_map(|[a, b, c]| {
+                let ab = middle(*a, *b, &mut vertices);
+                let bc = middle(*b, *c, &mut vertices);
+                let ca = middle(*c, *a, &mut vertices);
+                [[*a, ab, ca], [*b, bc, ab], [*c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut mesh = Mesh::new();
+    for v in vertices.iter() {
+        mesh.add_vertex(center + &(v * radius));
+    }
+    for [a, b, c] in triangles {
+        mesh.add_triangle(a, b, c, color);
+    }
+
+    mesh
+}
+
+// `base` is the centre of the bottom cap
+pub fn cylinder(base: &Point3D, radius: f32, height: f32, segments: usize, color: Color) -> Mesh {
+    let segments = segments.max(3);
+    let mut mesh = Mesh::new();
+
+    let top_ring = ring(base, radius, -height, segments);
+    let bottom_ring = ring(base, radius, 0., segments);
+
+    let top: Vec<usize> = top_ring
+        .iter()
+        .map(|p| mesh.add_vertex(p.clone()))
+        .collect();
+    let bottom: Vec<usize> = bottom_ring
+        .iter()
+        .map(|p| mesh.add_vertex(p.clone()))
+        .collect();
+
+    for j in 0..segments {
+        let k = (j + 1) % segments;
+        mesh.add_face(&[top[j], bottom[j], bottom[k], top[k]], color);
+    }
+
+    add_cap(&mut mesh, &top_ring, true, color);
+    add_cap(&mut mesh, &bottom_ring, false, color);
+
+    mesh
+}
+
+// `base` is the centre of the bottom cap, the apex is `height` above it
+pub fn cone(base: &Point3D, radius: f32, height: f32, segments: usize, color: Color) -> Mesh {
+    let segments = segments.max(3);
+    let apex = base + &Point3D::new(0., -height, 0.);
+
+    pointed(&apex, &ring(base, radius, 0., segments), color)
+}
+
+// Square based pyramid, `base` is the centre of its base
+pub fn pyramid(base: &Point3D, size: f32, height: f32, color: Color) -> Mesh {
+    let h = size / 2.;
+    let apex = base + &Point3D::new(0., -height, 0.);
+
+    // Same turning direction as `ring`
+    let corners = [
+        base + &Point3D::new(h, 0., -h),
+        base + &Point3D::new(h, 0., h),
+        base + &Point3D::new(-h, 0., h),
+        base + &Point3D::new(-h, 0., -h),
+    ];
+
+    pointed(&apex, &corners, color)
+}
+
+// Lies flat, facing up. Spans `width` along X and `depth` along Z
+pub fn plane_grid(
+    center: &Point3D,
+    width: f32,
+    depth: f32,
+    columns: usize,
+    rows: usize,
+    color: Color,
+) -> Mesh {
+    let columns = columns.max(1);
+    let rows = rows.max(1);
+
+    let mut mesh = Mesh::new();
+    let origin = center - &Point3D::new(width / 2., 0., depth / 2.);
+
+    for j in 0..=rows {
+        for i in 0..=columns {
+            let offset = Point3D::new(
+                width * i as f32 / columns as f32,
+                0.,
+                depth * j as f32 / rows as f32,
+            );
+            mesh.add_vertex(&origin + &offset);
+        }
+    }
+
+    let index = |i: usize, j: usize| j * (columns + 1) + i;
+    for j in 0..rows {
+        for i in 0..columns {
+            mesh.add_face(
+                &[
+                    index(i, j),
+                    index(i + 1, j),
+                    index(i + 1, j + 1),
+                    index(i, j + 1),
+                ],
+                color,
+            );
+        }
+    }
+
+    mesh
+}
+
+// Lies flat around `center`. `segments` go around the hole, `sides` around the tube
+pub fn torus(
+    center: &Point3D,
+    major_radius: f32,
+    minor_radius: f32,
+    segments: usize,
+    sides: usize,
+    color: Color,
+) -> Mesh {
+    let segments = segments.max(3);
+    let sides = sides.max(3);
+
+    let mut mesh = Mesh::new();
+
+    for i in 0..segments {
+        let u = 2. * PI * i as f32 / segments as f32;
+        for j in 0..sides {
+            let v = 2. * PI * j as f32 / sides as f32;
+            let r = major_radius + minor_radius * v.cos();
+            mesh.add_vertex(
+                center + &Point3D::new(r * u.cos(), minor_radius * v.sin(), r * u.sin()),
+            );
+        }
+    }
+
+    let index = |i: usize, j: usize| (i % segments) * sides + j % sides;
+    for i in 0..segments {
+        for j in 0..sides {
+            mesh.add_face(
+                &[
+                    index(i, j),
+                    index(i, j + 1),
+                    index(i + 1, j + 1),
+                    index(i + 1, j),
+                ],
+                color,
+            );
+        }
+    }
+
+    mesh
+}
+
+// Horizontal circle `dy` below `center`
+fn ring(center: &Point3D, radius: f32, dy: f32, segments: usize) -> Vec<Point3D> {
+    (0..segments)
+        .map(|j| {
+            let phi = 2. * PI * j as f32 / segments as f32;
+            center + &Point3D::new(radius * phi.cos(), dy, radius * phi.sin())
+        })
+        .collect()
+}
+
+// Sides going from the base outline up to the apex, plus the base itself
+fn pointed(apex: &Point3D, outline: &[Point3D], color: Color) -> Mesh {
+    let mut mesh = Mesh::new();
+
+    let top = mesh.add_vertex(apex.clone());
+    let base: Vec<usize> = outline.iter().map(|p| mesh.add_vertex(p.clone())).collect();
+
+    for j in 0..base.len() {
+        mesh.add_triangle(top, base[j], base[(j + 1) % base.len()], color);
+    }
+
+    add_cap(&mut mesh, outline, false, color);
+
+    mesh
+}
+
+// Flat horizontal n-gon with its own vertices, facing up or down
+fn add_cap(mesh: &mut Mesh, outline: &[Point3D], up: bool, color: Color) {
+    let mut indices: Vec<usize> = outline.iter().map(|p| mesh.add_vertex(p.clone())).collect();
+    if !up {
+        indices.reverse();
+    }
+
+    mesh.add_face(&indices, color);
+}
@@ -0,0 +1,291 @@
+// STL import and export, ASCII and binary
+//
+// STL files are Z up, we are Y down: (x, y, z) in the file is (x, -z, y) here.
+// Like in the rest of the crate, facets are counter-clockwise seen from outside
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use sdl2::pixels::Color;
+
+use crate::{Mesh, Point3D};
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(PathBuf, io::Error),
+    Ascii { line: usize, message: String },
+    Binary(String),
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            StlError::Ascii { line, message } => write!(f, "line {}: {}", line, message),
+            StlError::Binary(message) => write!(f, "binary STL: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StlError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+const HEADER_SIZE: usize = 80;
+const FACET_SIZE: usize = 50;
+
+pub fn load_stl(path: &Path, color: Color) -> Result<Mesh, StlError> {
+    let data = fs::read(path).map_err(|e| StlError::Io(path.to_path_buf(), e))?;
+    parse_stl(&data, color)
+}
+
+// Binary files are recognised by their size matching their facet count, since
+// some exporters also start binary headers with "solid"
+pub fn parse_stl(data: &[u8], color: Color) -> Result<Mesh, StlError> {
+    if data.len() >= HEADER_SIZE + 4 {
+        let count = u32::from_le_bytes(data[HEADER_SIZE..HEADER_SIZE + 4].try_into().unwrap());
+        if data.len() == HEADER_SIZE + 4 + count as usize * FACET_SIZE {
+            return parse_binary(data, color);
+        }
+    }
+
+    if data.trim_ascii_start().starts_with(b"solid") {
+        let text = std::str::from_utf8(data).map_err(|e| StlError::Ascii {
+            line: 1 + data[..e.valid_up_to()]
+                .iter()
+                .filter(|b| **b == b'\n')
+                .count(),
+            message: "invalid UTF-8".to_string(),
+        })?;
+        return parse_ascii(text, color);
+    }
+
+    Err(StlError::Binary(format!(
+        "file size ({} bytes) does not match the facet count",
+        data.len()
+    )))
+}
+
+fn parse_binary(data: &[u8], color: Color) -> Result<Mesh, StlError> {
+    let mut builder = StlBuilder::new(color);
+
+    let read = |offset: usize| {
+        let v: Vec<f32> = (0..3)
+            .map(|i| {
+                let start = offset + i * 4;
+                f32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+            })
+            .collect();
+        from_stl(v[0], v[1], v[2])
+    };
+
+    for facet in 0..(data.len() - HEADER_SIZE - 4) / FACET_SIZE {
+        let offset = HEADER_SIZE + 4 + facet * FACET_SIZE;
+        let normal = read(offset);
+        let corners = [read(offset + 12), read(offset + 24), read(offset + 36)];
+
+        if corners
+            .iter()
+            .any(|c| !(c.x.is_finite() && c.y.is_finite() && c.z.is_finite()))
+        {
+            return Err(StlError::Binary(format!(
+                "facet {}: invalid coordinates",
+                facet
+            )));
+        }
+        builder.add_facet(&normal, corners);
+    }
+
+    Ok(builder.mesh)
+}
+
+fn parse_ascii(text: &str, color: Color) -> Result<Mesh, StlError> {
+    let mut builder = StlBuilder::new(color);
+
+    let mut normal = Point3D::ZERO;
+    let mut corners: Vec<Point3D> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: String| StlError::Ascii {
+            line: i + 1,
+            message,
+        };
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let numbers = |tokens: &[&str]| -> Result<Point3D, StlError> {
+            let v = tokens
+                .iter()
+                .map(|t| t.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error(format!("invalid number in `{}`", line.trim())))?;
+            match v[..] {
+                [x, y, z] => Ok(from_stl(x, y, z)),
+                _ => Err(error(format!("expected 3 numbers in `{}`", line.trim()))),
+            }
+        };
+
+        match tokens.as_slice() {
+            [] | ["solid", ..] | ["endsolid", ..] | ["outer", "loop"] => {}
+            ["facet", "normal", rest @ ..] => {
+                normal = numbers(rest)?;
+                corners.clear();
+            }
+            ["vertex", rest @ ..] => {
+                if corners.len() == 3 {
+                    return Err(error("more than 3 vertices in a facet".to_string()));
+                }
+                corners.push(numbers(rest)?);
+            }
+            ["endloop"] => {}
+            ["endfacet"] => {
+                let [a, b, c] = <[Point3D; 3]>::try_from(std::mem::take(&mut corners))
+                    .map_err(|v| error(format!("facet with {} vertices", v.len())))?;
+                builder.add_facet(&normal, [a, b, c]);
+            }
+            _ => return Err(error(format!("unexpected `{}`", line.trim()))),
+        }
+    }
+
+    Ok(builder.mesh)
+}
+
+pub fn write_stl_ascii<W: Write>(mesh: &Mesh, name: &str, out: &mut W) -> io::Result<()> {
+    writeln!(out, "solid {}", name)?;
+
+    for (normal, corners) in facets(mesh) {
+        let n = to_stl(&normal);
+        writeln!(out, "  facet normal {:e} {:e} {:e}", n[0], n[1], n[2])?;
+        writeln!(out, "    outer loop")?;
+        for corner in corners.iter() {
+            let v = to_stl(corner);
+            writeln!(out, "      vertex {:e} {:e} {:e}", v[0], v[1], v[2])?;
+        }
+        writeln!(out, "    endloop")?;
+        writeln!(out, "  endfacet")?;
+    }
+
+    writeln!(out, "endsolid {}", name)
+}
+
+pub fn write_stl_binary<W: Write>(mesh: &Mesh, out: &mut W) -> io::Result<()> {
+    let mut header = [0u8; HEADER_SIZE];
+    let title = b"binary STL written by doom";
+    header[..title.len()].copy_from_slice(title);
+    out.write_all(&header)?;
+
+    let facets: Vec<(Point3D, [Point3D; 3])> = facets(mesh).collect();
+    out.write_all(&(facets.len() as u32).to_le_bytes())?;
+
+    for (normal, corners) in facets.iter() {
+        for v in std::iter::once(normal).chain(corners.iter()) {
+            for c in to_stl(v) {
+                out.write_all(&c.to_le_bytes())?;
+            }
+        }
+        // Attribute byte count, unused
+        out.write_all(&[0, 0])?;
+    }
+
+    Ok(())
+}
+
+// All the meshes end up in a single solid
+pub fn save_stl(meshes: &[Mesh], path: &Path, binary: bool) -> Result<(), StlError> {
+    let io_error = |e| StlError::Io(path.to_path_buf(), e);
+
+    let mut mesh = Mesh::new();
+    for m in meshes {
+        mesh.append(m);
+    }
+
+    let mut out = BufWriter::new(File::create(path).map_err(io_error)?);
+    if binary {
+        write_stl_binary(&mesh, &mut out).map_err(io_error)?;
+    } else {
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("doom");
+        write_stl_ascii(&mesh, name, &mut out).map_err(io_error)?;
+    }
+
+    out.flush().map_err(io_error)
+}
+
+// Triangles with their normal, computed like `Square::normal` then normalized
+fn facets(mesh: &Mesh) -> impl Iterator<Item = (Point3D, [Point3D; 3])> + '_ {
+    mesh.triangles().map(|([a, b, c], _)| {
+        let corners = [
+            mesh.vertices[a].clone(),
+            mesh.vertices[b].clone(),
+            mesh.vertices[c].clone(),
+        ];
+        let normal = (&corners[1] - &corners[0]) * (&corners[2] - &corners[0]);
+        let normal = if normal.norm() > 0. {
+            normal.normalize()
+        } else {
+            Point3D::ZERO
+        };
+
+        (normal, corners)
+    })
+}
+
+fn from_stl(x: f32, y: f32, z: f32) -> Point3D {
+    Point3D::new(x, -z, y)
+}
+
+fn to_stl(p: &Point3D) -> [f32; 3] {
+    [p.x, p.z, -p.y]
+}
+
+// Merges the corners that are exactly at the same place
+struct StlBuilder {
+    mesh: Mesh,
+    color: Color,
+    indices: HashMap<[u32; 3], usize>,
+}
+
+impl StlBuilder {
+    fn new(color: Color) -> StlBuilder {
+        StlBuilder {
+            mesh: Mesh::new(),
+            color,
+            indices: HashMap::new(),
+        }
+    }
+
+    // The corners keep their order, unless the stored normal points the other
+    // way: then they are flipped to match it. A zero normal leaves them as is
+    fn add_facet(&mut self, normal: &Point3D, corners: [Point3D; 3]) {
+        let [a, b, c] = corners.map(|p| self.vertex(p));
+
+        let mut face = [a, b, c];
+        let winding = (&self.mesh.vertices[b] - &self.mesh.vertices[a])
+            * (&self.mesh.vertices[c] - &self.mesh.vertices[a]);
+        if winding.dot(normal) < 0. {
+            face.swap(1, 2);
+        }
+
+        // Degenerate facets are dropped
+        if a != b && b != c && a != c {
+            self.mesh.add_face(&face, self.color);
+        }
+    }
+
+    fn vertex(&mut self, p: Point3D) -> usize {
+        let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        if let Some(i) = self.indices.get(&key) {
+            return *i;
+        }
+
+        let i = self.mesh.add_vertex(p);
+        self.indices.insert(key, i);
+        i
+    }
+}
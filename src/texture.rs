@@ -0,0 +1,528 @@
+// Images to map on faces
+//
+// Texture coordinates go from (0, 0) at the top left of the image to (1, 1)
+// at its bottom right, like the `uvs` of a mesh
+//
+// Every texture keeps a mip chain: copies of the image halved in size again
+// and again down to a single texel. Far away, a pixel covers many texels and
+// sampling only some of them makes the texture shimmer as the camera moves, so
+// the copy where a texel is about the size of the pixel is used instead
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sdl2::pixels::Color;
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(PathBuf, io::Error),
+    Format(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            TextureError::Format(message) => write!(f, "invalid image: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Io(_, e) => Some(e),
+            TextureError::Format(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    // Closest texel of the closest mip level: sharp, blocky up close
+    #[default]
+    Nearest,
+    // Weighted average of the 4 closest texels of the closest mip level
+    Bilinear,
+    // Bilinear in the two closest mip levels, blended: no visible jump
+    // where the mip level changes
+    Trilinear,
+}
+
+// What coordinates outside of 0..1 give
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Wrap {
+    // The image tiles
+    #[default]
+    Repeat,
+    // The border texels stretch out
+    Clamp,
+}
+
+// Texture coordinates of a pixel, and how much they change to the next pixel
+// right (`dx`) and down (`dy`): the part of the texture the pixel covers
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TexCoord {
+    pub u: f32,
+    pub v: f32,
+    pub dx: (f32, f32),
+    pub dy: (f32, f32),
+}
+
+impl TexCoord {
+    // A pixel covering nothing, which always gets the full size image
+    pub fn new(u: f32, v: f32) -> TexCoord {
+        TexCoord {
+            u,
+            v,
+            ..TexCoord::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MipLevel {
+    width: u32,
+    height: u32,
+    // Row by row, from the top
+    texels: Vec<Color>,
+}
+
+impl MipLevel {
+    // Each texel is the average of the (up to) 4 it covers in `self`
+    fn halved(&self) -> MipLevel {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut texels = Vec::with_capacity(width as usize * height as usize);
+
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = (x * 2, y * 2);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let covered = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+                    .map(|(x, y)| (self.texels[(y * self.width + x) as usize], 0.25));
+                texels.push(mix(&covered));
+            }
+        }
+
+        MipLevel {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64, wrap: Wrap) -> Color {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (x, y) = match wrap {
+            Wrap::Repeat => (x.rem_euclid(w), y.rem_euclid(h)),
+            Wrap::Clamp => (x.clamp(0, w - 1), y.clamp(0, h - 1)),
+        };
+        self.texels[(y * w + x) as usize]
+    }
+
+    fn nearest(&self, u: f32, v: f32, wrap: Wrap) -> Color {
+        let x = u * self.width as f32;
+        let y = v * self.height as f32;
+        self.texel(x.floor() as i64, y.floor() as i64, wrap)
+    }
+
+    fn bilinear(&self, u: f32, v: f32, wrap: Wrap) -> Color {
+        // Texel centres are at half coordinates
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        mix(&[
+            (self.texel(x0, y0, wrap), (1. - tx) * (1. - ty)),
+            (self.texel(x0 + 1, y0, wrap), tx * (1. - ty)),
+            (self.texel(x0, y0 + 1, wrap), (1. - tx) * ty),
+            (self.texel(x0 + 1, y0 + 1, wrap), tx * ty),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    // The image itself first, then each half the size of the previous one
+    levels: Vec<MipLevel>,
+    pub filter: Filter,
+    pub wrap: Wrap,
+}
+
+impl Texture {
+    pub fn new(width: u32, height: u32, texels: Vec<Color>) -> Texture {
+        assert!(width > 0 && height > 0, "empty texture");
+        assert_eq!(texels.len(), width as usize * height as usize);
+
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            texels,
+        }];
+        while let Some(last) = levels.last().filter(|l| l.width > 1 || l.height > 1) {
+            levels.push(last.halved());
+        }
+
+        Texture {
+            levels,
+            filter: Filter::default(),
+            wrap: Wrap::default(),
+        }
+    }
+
+    // Squares of `size` texels alternating between `a` and `b`, `count` of
+    // them along each side
+    pub fn checkerboard(size: u32, count: u32, a: Color, b: Color) -> Texture {
+        let side = size * count;
+        let texels = (0..side * side)
+            .map(|i| {
+                let (x, y) = (i % side / size, i / side / size);
+                if (x + y) % 2 == 0 {
+                    a
+                } else {
+                    b
+                }
+            })
+            .collect();
+
+        Texture::new(side, side, texels)
+    }
+
+    // Format picked from the extension: `.bmp` or `.ppm`
+    pub fn load(path: &Path) -> Result<Texture, TextureError> {
+        let bytes = fs::read(path).map_err(|e| TextureError::Io(path.to_path_buf(), e))?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("bmp") => Texture::from_bmp(&bytes),
+            Some("ppm") => Texture::from_ppm(&bytes),
+            _ => Err(TextureError::Format(format!(
+                "unsupported image format: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    // Uncompressed BMP, with 8 bits palettes, 24 or 32 bits per pixel
+    pub fn from_bmp(bytes: &[u8]) -> Result<Texture, TextureError> {
+        let format = |message: &str| TextureError::Format(message.to_string());
+        let u16_at = |at: usize| {
+            bytes
+                .get(at..at + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or(format("truncated BMP"))
+        };
+        let u32_at = |at: usize| {
+            bytes
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or(format("truncated BMP"))
+        };
+
+        if !bytes.starts_with(b"BM") {
+            return Err(format("not a BMP file"));
+        }
+        let data = u32_at(10)? as usize;
+        let header_size = u32_at(14)? as usize;
+        if header_size < 40 {
+            return Err(format("unsupported BMP header"));
+        }
+        let width = u32_at(18)? as i32;
+        let height = u32_at(22)? as i32;
+        let bits = u16_at(28)?;
+        let compression = u32_at(30)?;
+        // Bit fields with the usual masks are found in 32 bits files as well
+        if compression != 0 && !(compression == 3 && bits == 32) {
+            return Err(format("compressed BMP files are not supported"));
+        }
+        if width <= 0 || height == 0 {
+            return Err(format("empty BMP"));
+        }
+
+        let palette: Vec<Color> = if bits == 8 {
+            let count = match u32_at(46)? {
+                0 => 256,
+                n => n.min(256) as usize,
+            };
+            let start = 14 + header_size;
+            (0..count)
+                .map(|i| {
+                    let entry = bytes
+                        .get(start + i * 4..start + i * 4 + 3)
+                        .ok_or(format("truncated BMP palette"))?;
+                    Ok(Color::RGB(entry[2], entry[1], entry[0]))
+                })
+                .collect::<Result<_, TextureError>>()?
+        } else {
+            vec![]
+        };
+
+        let (width, rows) = (width as u32, height.unsigned_abs());
+        let bytes_per_pixel = match bits {
+            8 | 24 | 32 => bits as usize / 8,
+            _ => return Err(format("unsupported BMP pixel depth")),
+        };
+        // Rows are padded to 4 bytes, and stored from the bottom unless the
+        // height is negative
+        let stride = (width as usize * bytes_per_pixel).div_ceil(4) * 4;
+        // Checked before allocating, as the header can claim anything
+        let end = (rows as usize - 1)
+            .checked_mul(stride)
+            .and_then(|size| size.checked_add(data))
+            .and_then(|size| size.checked_add(width as usize * bytes_per_pixel))
+            .ok_or(format("invalid BMP size"))?;
+        if end > bytes.len() {
+            return Err(format("truncated BMP pixel data"));
+        }
+        let mut texels = Vec::with_capacity(width as usize * rows as usize);
+        for y in 0..rows {
+            let row = if height > 0 { rows - 1 - y } else { y };
+            let start = data + row as usize * stride;
+            let line = bytes
+                .get(start..start + width as usize * bytes_per_pixel)
+                .ok_or(format("truncated BMP pixel data"))?;
+            for pixel in line.chunks_exact(bytes_per_pixel) {
+                texels.push(match pixel {
+                    [i] => *palette
+                        .get(*i as usize)
+                        .ok_or(format("BMP colour index out of the palette"))?,
+                    [b, g, r, ..] => Color::RGB(*r, *g, *b),
+                    _ => unreachable!(),
+                });
+            }
+        }
+
+        Ok(Texture::new(width, rows, texels))
+    }
+
+    // Binary (P6) or plain (P3) PPM
+    pub fn from_ppm(bytes: &[u8]) -> Result<Texture, TextureError> {
+        let format = |message: &str| TextureError::Format(message.to_string());
+
+        let mut at = 0;
+        let magic = ppm_token(bytes, &mut at).ok_or(format("truncated PPM"))?;
+        let number = |at: &mut usize, name: &str| {
+            ppm_token(bytes, at)
+                .and_then(|t| t.parse::<u32>().ok())
+                .ok_or(TextureError::Format(format!("invalid PPM {}", name)))
+        };
+        let width = number(&mut at, "width")?;
+        let height = number(&mut at, "height")?;
+        let max = number(&mut at, "maximum value")?;
+        if width == 0 || height == 0 {
+            return Err(format("empty PPM"));
+        }
+        if max == 0 || max > 255 {
+            return Err(format("only 8 bits PPM files are supported"));
+        }
+
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|size| size.checked_mul(3))
+            .ok_or(format("invalid PPM size"))?;
+        let values: Vec<u32> = match magic {
+            "P6" => {
+                // A single whitespace character ends the header
+                let start = at + 1;
+                let end = start.checked_add(count).ok_or(format("invalid PPM size"))?;
+                bytes
+                    .get(start..end)
+                    .ok_or(format("truncated PPM pixel data"))?
+                    .iter()
+                    .map(|v| *v as u32)
+                    .collect()
+            }
+            "P3" => (0..count)
+                .map(|_| number(&mut at, "value"))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(format("not a PPM file")),
+        };
+
+        let scale = |v: u32| (v.min(max) * 255 / max) as u8;
+        let texels = values
+            .chunks_exact(3)
+            .map(|c| Color::RGB(scale(c[0]), scale(c[1]), scale(c[2])))
+            .collect();
+
+        Ok(Texture::new(width, height, texels))
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Texture {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: Wrap) -> Texture {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.levels[0].height
+    }
+
+    // Number of mip levels, the full size image included
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Of the full size image, wrapped or clamped to it
+    pub fn texel(&self, x: i64, y: i64) -> Color {
+        self.levels[0].texel(x, y, self.wrap)
+    }
+
+    // Mip level to sample at `coord`: 0 for the full size image, 1 for half
+    // of it... Fractional between levels, and never negative
+    pub fn lod(&self, coord: &TexCoord) -> f32 {
+        let (w, h) = (self.width() as f32, self.height() as f32);
+        let texels = |(du, dv): (f32, f32)| (du * w).hypot(dv * h);
+        let footprint = texels(coord.dx).max(texels(coord.dy));
+
+        if !footprint.is_finite() || footprint <= 1. {
+            return 0.;
+        }
+        footprint.log2().min((self.levels.len() - 1) as f32)
+    }
+
+    // From the full size image, whatever the distance
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        self.sample_at(&TexCoord::new(u, v))
+    }
+
+    // From the mip levels that suit the part of the texture covered
+    pub fn sample_at(&self, coord: &TexCoord) -> Color {
+        let (u, v) = (coord.u, coord.v);
+        if !u.is_finite() || !v.is_finite() {
+            return self.levels[0].texels[0];
+        }
+
+        let lod = self.lod(coord);
+        let closest = &self.levels[lod.round() as usize];
+        match self.filter {
+            Filter::Nearest => closest.nearest(u, v, self.wrap),
+            Filter::Bilinear => closest.bilinear(u, v, self.wrap),
+            Filter::Trilinear => {
+                let level = lod.floor() as usize;
+                let t = lod - level as f32;
+                let finer = self.levels[level].bilinear(u, v, self.wrap);
+                match self.levels.get(level + 1) {
+                    Some(coarser) if t > 0. => {
+                        mix(&[(finer, 1. - t), (coarser.bilinear(u, v, self.wrap), t)])
+                    }
+                    _ => finer,
+                }
+            }
+        }
+    }
+}
+
+// Weighted sum of colours, weights adding up to 1
+fn mix(colors: &[(Color, f32)]) -> Color {
+    let channel = |c: fn(&Color) -> u8| {
+        let sum: f32 = colors.iter().map(|(color, w)| c(color) as f32 * w).sum();
+        sum.round().clamp(0., 255.) as u8
+    };
+    Color::RGBA(
+        channel(|c| c.r),
+        channel(|c| c.g),
+        channel(|c| c.b),
+        channel(|c| c.a),
+    )
+}
+
+// Next field of a PPM header from `at`. Fields are separated by whitespace,
+// with comments up to the end of the line
+fn ppm_token<'a>(bytes: &'a [u8], at: &mut usize) -> Option<&'a str> {
+    loop {
+        match bytes.get(*at) {
+            Some(b'#') => {
+                while bytes.get(*at).is_some_and(|b| *b != b'\n') {
+                    *at += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => *at += 1,
+            _ => break,
+        }
+    }
+
+    let start = *at;
+    while bytes.get(*at).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *at += 1;
+    }
+    std::str::from_utf8(&bytes[start..*at])
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_error(result: Result<Texture, TextureError>) -> String {
+        match result {
+            Err(TextureError::Format(message)) => message,
+            Err(e) => panic!("expected a format error, got {}", e),
+            Ok(_) => panic!("expected a format error"),
+        }
+    }
+
+    // 24 bits BMP header, without any pixel data
+    fn bmp_header(width: i32, height: i32) -> Vec<u8> {
+        let mut bytes = vec![0; 54];
+        bytes[..2].copy_from_slice(b"BM");
+        bytes[10..14].copy_from_slice(&54u32.to_le_bytes());
+        bytes[14..18].copy_from_slice(&40u32.to_le_bytes());
+        bytes[18..22].copy_from_slice(&width.to_le_bytes());
+        bytes[22..26].copy_from_slice(&height.to_le_bytes());
+        bytes[28..30].copy_from_slice(&24u16.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn small_images() {
+        let ppm = Texture::from_ppm(b"P3 2 1 255\n255 0 0  0 0 255\n").unwrap();
+        assert_eq!((ppm.width(), ppm.height()), (2, 1));
+        assert_eq!(ppm.texel(0, 0), Color::RGB(255, 0, 0));
+        assert_eq!(ppm.texel(1, 0), Color::RGB(0, 0, 255));
+
+        // One row, padded to 4 bytes, in BGR order
+        let mut bmp = bmp_header(1, 1);
+        bmp.extend([0, 0, 255, 0]);
+        let bmp = Texture::from_bmp(&bmp).unwrap();
+        assert_eq!((bmp.width(), bmp.height()), (1, 1));
+        assert_eq!(bmp.texel(0, 0), Color::RGB(255, 0, 0));
+    }
+
+    #[test]
+    fn hostile_bmp_headers() {
+        let message = format_error(Texture::from_bmp(&bmp_header(i32::MAX, i32::MIN)));
+        assert_eq!(message, "truncated BMP pixel data");
+        let message = format_error(Texture::from_bmp(&bmp_header(100_000, 100_000)));
+        assert_eq!(message, "truncated BMP pixel data");
+        let message = format_error(Texture::from_bmp(&bmp_header(0, 10)));
+        assert_eq!(message, "empty BMP");
+    }
+
+    #[test]
+    fn hostile_ppm_headers() {
+        let message = format_error(Texture::from_ppm(b"P6 4294967295 4294967295 255\n\0\0\0"));
+        assert_eq!(message, "invalid PPM size");
+        let message = format_error(Texture::from_ppm(b"P6 100000 100000 255\n\0\0\0"));
+        assert_eq!(message, "truncated PPM pixel data");
+        let message = format_error(Texture::from_ppm(b"P3 100000 100000 255\n1 2 3"));
+        assert_eq!(message, "invalid PPM value");
+        let message = format_error(Texture::from_ppm(b"P6 0 10 255\n"));
+        assert_eq!(message, "empty PPM");
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{Mat4, Point3D, Quaternion};
+
+// Scale first, then rotate, then translate
+#[derive(Debug, Clone)]
+pub struct Transform {
+    pub translation: Point3D,
+    pub rotation: Quaternion,
+    pub scale: Point3D,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Point3D::ZERO,
+        rotation: Quaternion::IDENTITY,
+        scale: Point3D {
+            x: 1.,
+            y: 1.,
+            z: 1.,
+        },
+    };
+
+    pub fn new(translation: Point3D, rotation: Quaternion, scale: Point3D) -> Transform {
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn from_translation(translation: Point3D) -> Transform {
+        Transform {
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn from_rotation(rotation: Quaternion) -> Transform {
+        Transform {
+            rotation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn from_scale(scale: Point3D) -> Transform {
+        Transform {
+            scale,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::translation(&self.translation)
+            * Mat4::rotation(&self.rotation)
+            * Mat4::scale(&self.scale)
+    }
+
+    // Built from the parts rather than by inverting `matrix()`; a zero scale axis collapses to zero
+    pub fn inverse_matrix(&self) -> Mat4 {
+        let inv = |s: f32| if s == 0. { 0. } else { 1. / s };
+        let scale = Point3D::new(inv(self.scale.x), inv(self.scale.y), inv(self.scale.z));
+
+        Mat4::scale(&scale)
+            * Mat4::rotation(&self.rotation.inverse())
+            * Mat4::translation(&(&Point3D::ZERO - &self.translation))
+    }
+
+    // `self.then(other)` applies `self` first, then `other`
+    pub fn then(&self, other: &Transform) -> Mat4 {
+        other.matrix() * self.matrix()
+    }
+
+    pub fn transform_point(&self, p: &Point3D) -> Point3D {
+        let scaled = Point3D::new(p.x * self.scale.x, p.y * self.scale.y, p.z * self.scale.z);
+        &scaled.rotate_by(&self.rotation) + &self.translation
+    }
+
+    pub fn transform_direction(&self, d: &Point3D) -> Point3D {
+        let scaled = Point3D::new(d.x * self.scale.x, d.y * self.scale.y, d.z * self.scale.z);
+        scaled.rotate_by(&self.rotation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::IDENTITY
+    }
+}
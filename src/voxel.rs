@@ -0,0 +1,259 @@
+// Block world stored in fixed-size chunks
+//
+// Voxel (x, y, z) is the cube `Cube::new` would make at (x, y, z) * voxel_size,
+// so "up" is -Y here too. Meshing only keeps the faces between a block and an
+// empty cell, looking into neighbouring chunks at the borders, and can merge
+// neighbouring faces of the same colour into bigger rectangles
+
+use std::collections::HashMap;
+
+use sdl2::pixels::Color;
+
+use crate::{Cube, Mesh, Point3D};
+
+pub const CHUNK_SIZE: i32 = 16;
+
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+// For each face of `Cube::FACES`: the axis it is across, and whether it is on
+// the low (0) or high (1) side of the voxel
+const FACE_SIDES: [(usize, i32); 6] = [(1, 0), (1, 1), (0, 0), (0, 1), (2, 1), (2, 0)];
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    voxels: Vec<Option<Color>>,
+    count: usize,
+}
+
+impl Chunk {
+    fn new() -> Chunk {
+        Chunk {
+            voxels: vec![None; CHUNK_VOLUME],
+            count: 0,
+        }
+    }
+
+    fn index(local: [i32; 3]) -> usize {
+        ((local[2] * CHUNK_SIZE + local[1]) * CHUNK_SIZE + local[0]) as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoxelWorld {
+    pub voxel_size: f32,
+    chunks: HashMap<[i32; 3], Chunk>,
+}
+
+impl VoxelWorld {
+    pub fn new(voxel_size: f32) -> VoxelWorld {
+        VoxelWorld {
+            voxel_size,
+            chunks: HashMap::new(),
+        }
+    }
+
+    // Chunk holding a voxel, and the voxel's position inside it
+    pub fn chunk_of(pos: [i32; 3]) -> ([i32; 3], [i32; 3]) {
+        (
+            pos.map(|c| c.div_euclid(CHUNK_SIZE)),
+            pos.map(|c| c.rem_euclid(CHUNK_SIZE)),
+        )
+    }
+
+    pub fn get(&self, pos: [i32; 3]) -> Option<Color> {
+        let (key, local) = VoxelWorld::chunk_of(pos);
+        self.chunks
+            .get(&key)
+            .and_then(|chunk| chunk.voxels[Chunk::index(local)])
+    }
+
+    // `None` removes the block. Chunks left empty are dropped
+    pub fn set(&mut self, pos: [i32; 3], voxel: Option<Color>) {
+        let (key, local) = VoxelWorld::chunk_of(pos);
+
+        if voxel.is_none() && !self.chunks.contains_key(&key) {
+            return;
+        }
+
+        let chunk = self.chunks.entry(key).or_insert_with(Chunk::new);
+        let cell = &mut chunk.voxels[Chunk::index(local)];
+        match (cell.is_some(), voxel.is_some()) {
+            (false, true) => chunk.count += 1,
+            (true, false) => chunk.count -= 1,
+            _ => {}
+        }
+        *cell = voxel;
+
+        if chunk.count == 0 {
+            self.chunks.remove(&key);
+        }
+    }
+
+    // Every voxel from `min` to `max`, both included
+    pub fn fill(&mut self, min: [i32; 3], max: [i32; 3], voxel: Option<Color>) {
+        for z in min[2]..=max[2] {
+            for y in min[1]..=max[1] {
+                for x in min[0]..=max[0] {
+                    self.set([x, y, z], voxel);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(|c| c.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = [i32; 3]> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    // Changing a voxel can change the mesh of the chunks next to it, when it
+    // is on the border
+    pub fn chunk_mesh(&self, key: [i32; 3], greedy: bool) -> Mesh {
+        let mut builder = VoxelMeshBuilder::new(self.voxel_size);
+
+        if !self.chunks.contains_key(&key) {
+            return builder.mesh;
+        }
+
+        let origin = key.map(|c| c * CHUNK_SIZE);
+
+        for (face, (axis, side)) in FACE_SIDES.iter().enumerate() {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+            for layer in 0..CHUNK_SIZE {
+                // Colour of the visible faces in this layer, indexed by (u, v)
+                let mut mask: Vec<Option<Color>> = vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+
+                for j in 0..CHUNK_SIZE {
+                    for i in 0..CHUNK_SIZE {
+                        let mut pos = origin;
+                        pos[*axis] += layer;
+                        pos[u] += i;
+                        pos[v] += j;
+
+                        let mut neighbour = pos;
+                        neighbour[*axis] += 2 * side - 1;
+
+                        if self.get(neighbour).is_none() {
+                            mask[(j * CHUNK_SIZE + i) as usize] = self.get(pos);
+                        }
+                    }
+                }
+
+                let mut plane = origin;
+                plane[*axis] += layer + side;
+
+                for (i, j, w, h, color) in rectangles(&mut mask, greedy) {
+                    let mut start = plane;
+                    start[u] += i;
+                    start[v] += j;
+                    builder.add_face(face, start, (u, w), (v, h), color);
+                }
+            }
+        }
+
+        builder.mesh
+    }
+
+    // One mesh per chunk
+    pub fn meshes(&self, greedy: bool) -> Vec<Mesh> {
+        self.chunks()
+            .map(|key| self.chunk_mesh(key, greedy))
+            .filter(|m| !m.faces.is_empty())
+            .collect()
+    }
+}
+
+// Cuts the visible faces of a layer into rectangles (i, j, width, height, colour),
+// clearing the mask as it goes. Without `greedy` every face is its own rectangle
+fn rectangles(mask: &mut [Option<Color>], greedy: bool) -> Vec<(i32, i32, i32, i32, Color)> {
+    let at = |i: i32, j: i32| (j * CHUNK_SIZE + i) as usize;
+    let mut found = vec![];
+
+    for j in 0..CHUNK_SIZE {
+        for i in 0..CHUNK_SIZE {
+            let Some(color) = mask[at(i, j)] else {
+                continue;
+            };
+
+            let (mut w, mut h) = (1, 1);
+            if greedy {
+                while i + w < CHUNK_SIZE && mask[at(i + w, j)] == Some(color) {
+                    w += 1;
+                }
+                while j + h < CHUNK_SIZE && (i..i + w).all(|x| mask[at(x, j + h)] == Some(color)) {
+                    h += 1;
+                }
+            }
+
+            for y in j..j + h {
+                for x in i..i + w {
+                    mask[at(x, y)] = None;
+                }
+            }
+            found.push((i, j, w, h, color));
+        }
+    }
+
+    found
+}
+
+// Shares the corners between faces, keyed by their position on the voxel grid
+// and the way the face looks
+struct VoxelMeshBuilder {
+    mesh: Mesh,
+    voxel_size: f32,
+    // Faces looking different ways get their own corners, so that smooth
+    // shading does not round the edges of the voxels
+    indices: HashMap<([i32; 3], usize), usize>,
+}
+
+impl VoxelMeshBuilder {
+    fn new(voxel_size: f32) -> VoxelMeshBuilder {
+        VoxelMeshBuilder {
+            mesh: Mesh::new(),
+            voxel_size,
+            indices: HashMap::new(),
+        }
+    }
+
+    // Rectangle of `Cube::FACES[face]`, stretched `w` voxels along the `u`
+    // axis and `h` along `v`. Corner `k` of a cube is at +X if bit 0 of `k` is
+    // set, +Z for bit 1 and +Y for bit 2, so the winding is the cube's
+    fn add_face(
+        &mut self,
+        face: usize,
+        start: [i32; 3],
+        (u, w): (usize, i32),
+        (v, h): (usize, i32),
+        color: Color,
+    ) {
+        let corners = Cube::FACES[face].map(|k| {
+            let offset = [k & 1, (k >> 2) & 1, (k >> 1) & 1];
+
+            let mut pos = start;
+            pos[u] += offset[u] as i32 * w;
+            pos[v] += offset[v] as i32 * h;
+            self.vertex(pos, face)
+        });
+
+        self.mesh.add_face(&corners, color);
+    }
+
+    fn vertex(&mut self, pos: [i32; 3], face: usize) -> usize {
+        if let Some(i) = self.indices.get(&(pos, face)) {
+            return *i;
+        }
+
+        let [x, y, z] = pos.map(|c| c as f32 * self.voxel_size);
+        let i = self.mesh.add_vertex(Point3D::new(x, y, z));
+        self.indices.insert((pos, face), i);
+        i
+    }
+}
@@ -0,0 +1,1023 @@
+// Doom WAD files and maps
+//
+// A WAD is a directory of named lumps. A map is a marker lump (E1M1, MAP01...)
+// followed by its own lumps, of which VERTEXES, LINEDEFS, SIDEDEFS, SECTORS and
+// THINGS are read. Everything is little-endian.
+//
+// Maps are flat with heights: (x, y) on the ground and z up. Here that is
+// (x, -z, y), a rotation, so Doom's north is +Z and east is +X
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sdl2::pixels::Color;
+
+use crate::{color_mul, Level, LevelObject, Mesh, Palette, Point3D, Transform};
+
+#[derive(Debug)]
+pub enum WadError {
+    Io(PathBuf, io::Error),
+    Format(String),
+}
+
+impl fmt::Display for WadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WadError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            WadError::Format(message) => write!(f, "invalid WAD: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for WadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WadError::Io(_, e) => Some(e),
+            WadError::Format(_) => None,
+        }
+    }
+}
+
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 16;
+
+// Lumps that can follow a map marker
+const MAP_LUMPS: [&str; 11] = [
+    "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS", "NODES", "SECTORS", "REJECT",
+    "BLOCKMAP", "BEHAVIOR",
+];
+
+const NO_SIDEDEF: u16 = 0xFFFF;
+const PLAYER_START: u16 = 1;
+const EYE_HEIGHT: f32 = 41.;
+const SKY_FLAT: &str = "F_SKY1";
+
+#[derive(Debug, Clone)]
+pub struct Lump {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Wad {
+    // IWADs are the games' main data files, PWADs are add-ons
+    pub iwad: bool,
+    pub lumps: Vec<Lump>,
+}
+
+impl Wad {
+    pub fn load(path: &Path) -> Result<Wad, WadError> {
+        let data = fs::read(path).map_err(|e| WadError::Io(path.to_path_buf(), e))?;
+        Wad::parse(&data)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Wad, WadError> {
+        if data.len() < HEADER_SIZE {
+            return Err(WadError::Format("file too short".to_string()));
+        }
+
+        let iwad = match &data[..4] {
+            b"IWAD" => true,
+            b"PWAD" => false,
+            _ => return Err(WadError::Format("not a WAD file".to_string())),
+        };
+
+        let count = read_i32(data, 4);
+        let directory = read_i32(data, 8);
+        if count < 0
+            || directory < 0
+            || directory as usize + count as usize * ENTRY_SIZE > data.len()
+        {
+            return Err(WadError::Format("directory out of the file".to_string()));
+        }
+
+        let lumps = (0..count as usize)
+            .map(|i| {
+                let entry = directory as usize + i * ENTRY_SIZE;
+                let (start, size) = (read_i32(data, entry), read_i32(data, entry + 4));
+                let name = read_name(data, entry + 8);
+
+                if start < 0 || size < 0 || start as usize + size as usize > data.len() {
+                    return Err(WadError::Format(format!("lump {} out of the file", name)));
+                }
+
+                let start = start as usize;
+                Ok(Lump {
+                    name,
+                    data: data[start..start + size as usize].to_vec(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Wad { iwad, lumps })
+    }
+
+    // Later lumps replace earlier ones of the same name, as in the game
+    pub fn lump(&self, name: &str) -> Option<&Lump> {
+        self.lumps.iter().rev().find(|l| l.name == name)
+    }
+
+    // From the PLAYPAL and COLORMAP lumps, None without a PLAYPAL
+    pub fn palette(&self) -> Result<Option<Palette>, WadError> {
+        let Some(playpal) = self.lump("PLAYPAL") else {
+            return Ok(None);
+        };
+        let colormap = self.lump("COLORMAP").map(|l| l.data.as_slice());
+        Palette::from_lumps(&playpal.data, colormap).map(Some)
+    }
+
+    // Markers directly followed by map lumps
+    pub fn map_names(&self) -> Vec<String> {
+        self.lumps
+            .windows(2)
+            .filter(|w| w[1].name == "THINGS" && !MAP_LUMPS.contains(&w[0].name.as_str()))
+            .map(|w| w[0].name.clone())
+            .collect()
+    }
+
+    pub fn map(&self, name: &str) -> Result<WadMap, WadError> {
+        let marker = self
+            .lumps
+            .iter()
+            .rposition(|l| l.name == name)
+            .ok_or(WadError::Format(format!("no map {}", name)))?;
+
+        let lumps: HashMap<&str, &Lump> = self.lumps[marker + 1..]
+            .iter()
+            .take_while(|l| MAP_LUMPS.contains(&l.name.as_str()))
+            .map(|l| (l.name.as_str(), l))
+            .collect();
+
+        if lumps.contains_key("BEHAVIOR") {
+            return Err(WadError::Format(format!(
+                "{}: Hexen format maps are not supported",
+                name
+            )));
+        }
+
+        let lump = |lump_name: &str| {
+            lumps
+                .get(lump_name)
+                .copied()
+                .ok_or(WadError::Format(format!("{}: no {} lump", name, lump_name)))
+        };
+
+        let map = WadMap {
+            name: name.to_string(),
+            vertices: records(lump("VERTEXES")?, 4, |r| MapVertex {
+                x: read_i16(r, 0),
+                y: read_i16(r, 2),
+            })?,
+            linedefs: records(lump("LINEDEFS")?, 14, |r| {
+                let side = |offset| match read_u16(r, offset) {
+                    NO_SIDEDEF => None,
+                    s => Some(s as usize),
+                };
+                Linedef {
+                    start: read_u16(r, 0) as usize,
+                    end: read_u16(r, 2) as usize,
+                    flags: read_u16(r, 4),
+                    special: read_u16(r, 6),
+                    tag: read_u16(r, 8),
+                    front: side(10),
+                    back: side(12),
+                }
+            })?,
+            sidedefs: records(lump("SIDEDEFS")?, 30, |r| Sidedef {
+                x_offset: read_i16(r, 0),
+                y_offset: read_i16(r, 2),
+                upper: read_name(r, 4),
+                lower: read_name(r, 12),
+                middle: read_name(r, 20),
+                sector: read_u16(r, 28) as usize,
+            })?,
+            sectors: records(lump("SECTORS")?, 26, |r| Sector {
+                floor_height: read_i16(r, 0),
+                ceiling_height: read_i16(r, 2),
+                floor_texture: read_name(r, 4),
+                ceiling_texture: read_name(r, 12),
+                light: read_i16(r, 20),
+                special: read_u16(r, 22),
+                tag: read_u16(r, 24),
+            })?,
+            things: records(lump("THINGS")?, 10, |r| Thing {
+                x: read_i16(r, 0),
+                y: read_i16(r, 2),
+                angle: read_i16(r, 4),
+                kind: read_u16(r, 6),
+                flags: read_u16(r, 8),
+            })?,
+        };
+
+        map.validate()?;
+        Ok(map)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MapVertex {
+    pub x: i16,
+    pub y: i16,
+}
+
+// The front sidedef is on the right going from `start` to `end`
+#[derive(Debug, Clone)]
+pub struct Linedef {
+    pub start: usize,
+    pub end: usize,
+    pub flags: u16,
+    pub special: u16,
+    pub tag: u16,
+    pub front: Option<usize>,
+    pub back: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sidedef {
+    pub x_offset: i16,
+    pub y_offset: i16,
+    pub upper: String,
+    pub lower: String,
+    pub middle: String,
+    pub sector: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sector {
+    pub floor_height: i16,
+    pub ceiling_height: i16,
+    pub floor_texture: String,
+    pub ceiling_texture: String,
+    // 0 (black) to 255
+    pub light: i16,
+    pub special: u16,
+    pub tag: u16,
+}
+
+// `angle` is in degrees, counter-clockwise from east
+#[derive(Debug, Clone)]
+pub struct Thing {
+    pub x: i16,
+    pub y: i16,
+    pub angle: i16,
+    pub kind: u16,
+    pub flags: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct WadMap {
+    pub name: String,
+    pub vertices: Vec<MapVertex>,
+    pub linedefs: Vec<Linedef>,
+    pub sidedefs: Vec<Sidedef>,
+    pub sectors: Vec<Sector>,
+    pub things: Vec<Thing>,
+}
+
+impl WadMap {
+    fn validate(&self) -> Result<(), WadError> {
+        let error = |message: String| Err(WadError::Format(format!("{}: {}", self.name, message)));
+
+        for (i, line) in self.linedefs.iter().enumerate() {
+            for v in [line.start, line.end] {
+                if v >= self.vertices.len() {
+                    return error(format!("linedef {}: no vertex {}", i, v));
+                }
+            }
+            for s in [line.front, line.back].into_iter().flatten() {
+                if s >= self.sidedefs.len() {
+                    return error(format!("linedef {}: no sidedef {}", i, s));
+                }
+            }
+        }
+
+        for (i, side) in self.sidedefs.iter().enumerate() {
+            if side.sector >= self.sectors.len() {
+                return error(format!("sidedef {}: no sector {}", i, side.sector));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Walls, floors and ceilings, facing the inside of their sector. Skies
+    // are left open. Colours are picked from the texture names and darkened
+    // with the sector's light level
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new();
+
+        for line in self.linedefs.iter() {
+            let sides = [
+                (line.front, line.back, line.start, line.end),
+                (line.back, line.front, line.end, line.start),
+            ];
+
+            for (side, other, a, b) in sides {
+                let Some(side) = side else {
+                    continue;
+                };
+                let side = &self.sidedefs[side];
+                let sector = &self.sectors[side.sector];
+
+                let mut wall = |bottom: i16, top: i16, texture: &str| {
+                    self.add_wall(&mut mesh, a, b, bottom, top, shade(texture, sector.light));
+                };
+
+                match other.map(|o| &self.sectors[self.sidedefs[o].sector]) {
+                    None => wall(sector.floor_height, sector.ceiling_height, &side.middle),
+                    Some(o) => {
+                        if o.floor_height > sector.floor_height {
+                            wall(sector.floor_height, o.floor_height, &side.lower);
+                        }
+                        let sky =
+                            o.ceiling_texture == SKY_FLAT && sector.ceiling_texture == SKY_FLAT;
+                        if o.ceiling_height < sector.ceiling_height && !sky {
+                            wall(o.ceiling_height, sector.ceiling_height, &side.upper);
+                        }
+                    }
+                }
+            }
+        }
+
+        let up = Point3D::new(0., -1., 0.);
+
+        for (i, sector) in self.sectors.iter().enumerate() {
+            let floor = shade(&sector.floor_texture, sector.light);
+            let ceiling = shade(&sector.ceiling_texture, sector.light);
+
+            for polygon in self.sector_polygons(i) {
+                for [a, b, c] in triangulate(&polygon) {
+                    let corners = [polygon[a], polygon[b], polygon[c]];
+                    let at = |h: i16| corners.map(|[x, y]| Point3D::new(x, -(h as f32), y));
+
+                    add_facing(&mut mesh, &at(sector.floor_height), &up, floor);
+                    if sector.ceiling_texture != SKY_FLAT {
+                        add_facing(&mut mesh, &at(sector.ceiling_height), &(&up * -1.), ceiling);
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+
+    // Seen from the sector on the right of `a` to `b`
+    fn add_wall(&self, mesh: &mut Mesh, a: usize, b: usize, bottom: i16, top: i16, color: Color) {
+        let (a, b) = (self.vertices[a], self.vertices[b]);
+        let at = |v: MapVertex, h: i16| Point3D::new(v.x as f32, -(h as f32), v.y as f32);
+
+        let inwards = Point3D::new(b.y as f32 - a.y as f32, 0., a.x as f32 - b.x as f32);
+        add_facing(
+            mesh,
+            &[at(a, bottom), at(b, bottom), at(b, top), at(a, top)],
+            &inwards,
+            color,
+        );
+    }
+
+    // Sides of the linedefs with this sector on their right. Lines with the
+    // sector on both sides do not bound it
+    fn sector_edges(&self, sector: usize) -> Vec<(usize, usize)> {
+        let sector_of = |side: Option<usize>| side.map(|s| self.sidedefs[s].sector);
+
+        self.linedefs
+            .iter()
+            .filter_map(|line| {
+                let (front, back) = (sector_of(line.front), sector_of(line.back));
+                match (front == Some(sector), back == Some(sector)) {
+                    (true, false) => Some((line.start, line.end)),
+                    (false, true) => Some((line.end, line.start)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    // Counter-clockwise polygons seen from above, one per separate part of
+    // the sector, with their holes joined to them by bridges. Loops that do
+    // not close are dropped
+    fn sector_polygons(&self, sector: usize) -> Vec<Vec<[f32; 2]>> {
+        let edges = self.sector_edges(sector);
+        let point = |v: usize| [self.vertices[v].x as f32, self.vertices[v].y as f32];
+
+        let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, (start, _)) in edges.iter().enumerate() {
+            outgoing.entry(*start).or_default().push(i);
+        }
+
+        let mut used = vec![false; edges.len()];
+        let mut loops: Vec<Vec<[f32; 2]>> = vec![];
+
+        for first in 0..edges.len() {
+            if used[first] {
+                continue;
+            }
+
+            let mut chain = vec![];
+            let mut edge = Some(first);
+            while let Some(e) = edge {
+                used[e] = true;
+                chain.push(point(edges[e].0));
+
+                let end = edges[e].1;
+                if end == edges[first].0 {
+                    // Sector on the right means clockwise around the outside
+                    // and counter-clockwise around holes: make it the other
+                    // way round, as `triangulate` expects
+                    chain.reverse();
+                    loops.push(chain);
+                    break;
+                }
+                edge = outgoing
+                    .get(&end)
+                    .and_then(|next| next.iter().find(|n| !used[**n]).copied());
+            }
+        }
+
+        let (mut outers, holes): (Vec<_>, Vec<_>) = loops
+            .into_iter()
+            .filter(|l| l.len() >= 3)
+            .partition(|l| signed_area(l) > 0.);
+
+        // Each hole goes in the smallest loop around it, rightmost holes first
+        let mut holes: Vec<(usize, Vec<[f32; 2]>)> = holes
+            .into_iter()
+            .filter_map(|hole| {
+                let owner = (0..outers.len())
+                    .filter(|o| contains(&outers[*o], hole[0]))
+                    .min_by(|a, b| signed_area(&outers[*a]).total_cmp(&signed_area(&outers[*b])))?;
+                Some((owner, hole))
+            })
+            .collect();
+        holes.sort_by(|a, b| max_x(&b.1).total_cmp(&max_x(&a.1)));
+
+        for i in 0..holes.len() {
+            let (owner, hole) = &holes[i];
+            let others: Vec<&[[f32; 2]]> = holes[i + 1..]
+                .iter()
+                .filter(|(o, _)| o == owner)
+                .map(|(_, h)| h.as_slice())
+                .collect();
+            outers[*owner] = bridge(&outers[*owner], hole, &others);
+        }
+
+        outers
+    }
+
+    // Sector containing a point, if any
+    pub fn sector_at(&self, x: f32, y: f32) -> Option<usize> {
+        (0..self.sectors.len()).find(|s| {
+            let mut inside = false;
+            for (a, b) in self.sector_edges(*s) {
+                let (a, b) = (self.vertices[a], self.vertices[b]);
+                let (ax, ay, bx, by) = (a.x as f32, a.y as f32, b.x as f32, b.y as f32);
+                if (ay > y) != (by > y) && x < ax + (y - ay) / (by - ay) * (bx - ax) {
+                    inside = !inside;
+                }
+            }
+            inside
+        })
+    }
+
+    // Eye position and yaw (radians, for `Camera::yaw`) of player 1's start
+    pub fn player_start(&self) -> Option<(Point3D, f32)> {
+        let thing = self.things.iter().find(|t| t.kind == PLAYER_START)?;
+        let (x, y) = (thing.x as f32, thing.y as f32);
+
+        let floor = self
+            .sector_at(x, y)
+            .map_or(0., |s| self.sectors[s].floor_height as f32);
+
+        // Yaw 0 looks north, and turns right (towards east) when positive
+        let yaw = (90. - thing.angle as f32).to_radians();
+        Some((Point3D::new(x, -(floor + EYE_HEIGHT), y), yaw))
+    }
+
+    pub fn level(&self) -> Level {
+        let (spawn, spawn_yaw) = self.player_start().unwrap_or((Point3D::ZERO, 0.));
+
+        Level {
+            objects: vec![LevelObject {
+                name: self.name.clone(),
+                transform: Transform::IDENTITY,
+                mesh: self.to_mesh(),
+            }],
+            spawn,
+            spawn_yaw,
+            ..Level::default()
+        }
+    }
+}
+
+// Keeps the winding whose normal goes the same way as `facing`
+fn add_facing(mesh: &mut Mesh, corners: &[Point3D], facing: &Point3D, color: Color) {
+    let mut indices: Vec<usize> = corners.iter().map(|p| mesh.add_vertex(p.clone())).collect();
+
+    let normal = (&corners[1] - &corners[0]) * (&corners[corners.len() - 1] - &corners[0]);
+    if normal.dot(facing) < 0. {
+        indices.reverse();
+    }
+
+    mesh.add_face(&indices, color);
+}
+
+// Stable colour for a texture, as there is no texture mapping yet
+fn shade(texture: &str, light: i16) -> Color {
+    // FNV-1a
+    let hash = texture.bytes().fold(0x811c9dc5u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x01000193)
+    });
+    let channel = |shift: u32| 96 + ((hash >> shift) & 0x7f) as u8;
+    let base = Color::RGB(channel(0), channel(8), channel(16));
+
+    color_mul(&base, light.clamp(0, 255) as f32 / 255.)
+}
+
+// Ear clipping of a counter-clockwise polygon. Bridges give repeated
+// positions, which never count as being inside an ear
+fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = vec![];
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            )
+        };
+
+        let ear = (0..n).find(|i| {
+            let (a, b, c) = corner(*i);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            cross(pa, pb, pc) > 0.
+                && !remaining.iter().any(|j| {
+                    let p = points[*j];
+                    p != pa && p != pb && p != pc && in_triangle(p, pa, pb, pc)
+                })
+        });
+
+        // Without any ear left the polygon is degenerate: flat corners are
+        // dropped first, then anything goes, so it always finishes
+        let i = match ear {
+            Some(i) => {
+                let (a, b, c) = corner(i);
+                triangles.push([a, b, c]);
+                i
+            }
+            None => (0..n)
+                .find(|i| {
+                    let (a, b, c) = corner(*i);
+                    cross(points[a], points[b], points[c]) == 0.
+                })
+                .unwrap_or(0),
+        };
+        remaining.remove(i);
+    }
+
+    if let [a, b, c] = remaining[..] {
+        if cross(points[a], points[b], points[c]) > 0. {
+            triangles.push([a, b, c]);
+        }
+    }
+
+    triangles
+}
+
+// Joins a clockwise hole to a counter-clockwise polygon through the hole's
+// rightmost vertex and the closest polygon vertex it can see
+fn bridge(polygon: &[[f32; 2]], hole: &[[f32; 2]], others: &[&[[f32; 2]]]) -> Vec<[f32; 2]> {
+    let m = (0..hole.len())
+        .max_by(|a, b| hole[*a][0].total_cmp(&hole[*b][0]))
+        .unwrap();
+    let pm = hole[m];
+
+    let distance = |p: [f32; 2]| (p[0] - pm[0]).powi(2) + (p[1] - pm[1]).powi(2);
+    let mut candidates: Vec<usize> = (0..polygon.len()).collect();
+    candidates.sort_by(|a, b| distance(polygon[*a]).total_cmp(&distance(polygon[*b])));
+
+    let blocked = |pv: [f32; 2]| {
+        std::iter::once(polygon)
+            .chain(std::iter::once(hole))
+            .chain(others.iter().copied())
+            .any(|ring| {
+                (0..ring.len()).any(|i| {
+                    let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                    crosses(pm, pv, a, b)
+                })
+            })
+    };
+
+    let v = candidates
+        .into_iter()
+        .find(|v| !blocked(polygon[*v]))
+        .unwrap_or(0);
+
+    let mut joined = polygon[..=v].to_vec();
+    joined.extend(hole[m..].iter());
+    joined.extend(hole[..=m].iter());
+    joined.extend(polygon[v..].iter());
+    joined
+}
+
+// Whether the segments cross, not counting shared ends
+fn crosses(p: [f32; 2], q: [f32; 2], a: [f32; 2], b: [f32; 2]) -> bool {
+    if p == a || p == b || q == a || q == b {
+        return false;
+    }
+
+    let (d1, d2) = (cross(p, q, a), cross(p, q, b));
+    let (d3, d4) = (cross(a, b, p), cross(a, b, q));
+    (d1 > 0.) != (d2 > 0.) && (d3 > 0.) != (d4 > 0.)
+}
+
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn in_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    cross(a, b, p) >= 0. && cross(b, c, p) >= 0. && cross(c, a, p) >= 0.
+}
+
+// Positive for counter-clockwise loops
+fn signed_area(points: &[[f32; 2]]) -> f32 {
+    (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum::<f32>()
+        / 2.
+}
+
+fn contains(polygon: &[[f32; 2]], p: [f32; 2]) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a[1] > p[1]) != (b[1] > p[1])
+            && p[0] < a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn max_x(points: &[[f32; 2]]) -> f32 {
+    points.iter().map(|p| p[0]).fold(f32::MIN, f32::max)
+}
+
+fn records<T, F: Fn(&[u8]) -> T>(lump: &Lump, size: usize, read: F) -> Result<Vec<T>, WadError> {
+    if !lump.data.len().is_multiple_of(size) {
+        return Err(WadError::Format(format!(
+            "{} is not made of {} byte records",
+            lump.name, size
+        )));
+    }
+
+    Ok(lump.data.chunks_exact(size).map(read).collect())
+}
+
+fn read_i16(data: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+// Names are up to 8 bytes, padded with zeros
+fn read_name(data: &[u8], offset: usize) -> String {
+    data[offset..offset + 8]
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| b.to_ascii_uppercase() as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONE: u16 = NO_SIDEDEF;
+
+    fn name(name: &str) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        bytes
+    }
+
+    // PWAD with the lumps in order, the directory after their data
+    fn wad(lumps: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut data: Vec<u8> = vec![];
+        let mut directory = vec![];
+        for (lump_name, lump) in lumps {
+            directory.extend(((HEADER_SIZE + data.len()) as i32).to_le_bytes());
+            directory.extend((lump.len() as i32).to_le_bytes());
+            directory.extend(name(lump_name));
+            data.extend(lump);
+        }
+
+        let mut wad = b"PWAD".to_vec();
+        wad.extend((lumps.len() as i32).to_le_bytes());
+        wad.extend(((HEADER_SIZE + data.len()) as i32).to_le_bytes());
+        wad.extend(data);
+        wad.extend(directory);
+        wad
+    }
+
+    fn words(values: &[i16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn vertexes(points: &[(i16, i16)]) -> Vec<u8> {
+        points.iter().flat_map(|(x, y)| words(&[*x, *y])).collect()
+    }
+
+    // Start, end, front and back sidedefs
+    fn linedefs(lines: &[(u16, u16, u16, u16)]) -> Vec<u8> {
+        lines
+            .iter()
+            .flat_map(|(start, end, front, back)| {
+                words(&[
+                    *start as i16,
+                    *end as i16,
+                    1,
+                    0,
+                    0,
+                    *front as i16,
+                    *back as i16,
+                ])
+            })
+            .collect()
+    }
+
+    // Middle textures only, in the given sectors
+    fn sidedefs(sectors: &[u16]) -> Vec<u8> {
+        let mut data = vec![];
+        for sector in sectors {
+            data.extend(words(&[0, 0]));
+            data.extend(name("-"));
+            data.extend(name("-"));
+            data.extend(name("STARTAN3"));
+            data.extend(words(&[*sector as i16]));
+        }
+        data
+    }
+
+    // Floor and ceiling heights
+    fn sectors(heights: &[(i16, i16)]) -> Vec<u8> {
+        let mut data = vec![];
+        for (floor, ceiling) in heights {
+            data.extend(words(&[*floor, *ceiling]));
+            data.extend(name("FLOOR4_8"));
+            data.extend(name("CEIL3_5"));
+            data.extend(words(&[160, 0, 0]));
+        }
+        data
+    }
+
+    // x, y and angle of player 1's start
+    fn things(x: i16, y: i16, angle: i16) -> Vec<u8> {
+        words(&[x, y, angle, PLAYER_START as i16, 7])
+    }
+
+    fn map_lumps(
+        vertices: Vec<u8>,
+        lines: Vec<u8>,
+        sides: Vec<u8>,
+        sector_data: Vec<u8>,
+    ) -> Vec<(&'static str, Vec<u8>)> {
+        vec![
+            ("MAP01", vec![]),
+            ("THINGS", things(64, 32, 90)),
+            ("LINEDEFS", lines),
+            ("SIDEDEFS", sides),
+            ("VERTEXES", vertices),
+            ("SECTORS", sector_data),
+        ]
+    }
+
+    // One 128 x 128 room. Going clockwise seen from above, the room is on the
+    // right of every line, where their front side is
+    fn square_room() -> Vec<(&'static str, Vec<u8>)> {
+        map_lumps(
+            vertexes(&[(0, 0), (0, 128), (128, 128), (128, 0)]),
+            linedefs(&[
+                (0, 1, 0, NONE),
+                (1, 2, 1, NONE),
+                (2, 3, 2, NONE),
+                (3, 0, 3, NONE),
+            ]),
+            sidedefs(&[0; 4]),
+            sectors(&[(0, 128)]),
+        )
+    }
+
+    // 256 x 256 room around a 64 x 64 pillar, which makes a hole in its floor
+    // and ceiling. The pillar's lines go counter-clockwise to have the room
+    // on their right
+    fn room_with_pillar() -> Vec<(&'static str, Vec<u8>)> {
+        map_lumps(
+            vertexes(&[
+                (0, 0),
+                (0, 256),
+                (256, 256),
+                (256, 0),
+                (96, 96),
+                (160, 96),
+                (160, 160),
+                (96, 160),
+            ]),
+            linedefs(&[
+                (0, 1, 0, NONE),
+                (1, 2, 1, NONE),
+                (2, 3, 2, NONE),
+                (3, 0, 3, NONE),
+                (4, 5, 4, NONE),
+                (5, 6, 5, NONE),
+                (6, 7, 6, NONE),
+                (7, 4, 7, NONE),
+            ]),
+            sidedefs(&[0; 8]),
+            sectors(&[(0, 128)]),
+        )
+    }
+
+    fn load(lumps: &[(&str, Vec<u8>)]) -> Result<WadMap, WadError> {
+        Wad::parse(&wad(lumps))?.map("MAP01")
+    }
+
+    fn format_error(result: Result<WadMap, WadError>) -> String {
+        match result {
+            Err(WadError::Format(message)) => message,
+            other => panic!("expected a format error, got {:?}", other.map(|m| m.name)),
+        }
+    }
+
+    fn replace(
+        mut lumps: Vec<(&'static str, Vec<u8>)>,
+        lump_name: &str,
+        data: Vec<u8>,
+    ) -> Vec<(&'static str, Vec<u8>)> {
+        lumps.iter_mut().find(|(n, _)| *n == lump_name).unwrap().1 = data;
+        lumps
+    }
+
+    // Face counts by kind: walls, floors and ceilings
+    fn count_faces(mesh: &Mesh) -> (usize, usize, usize) {
+        let normals = mesh.face_normals();
+        let count = |f: fn(&Point3D) -> bool| normals.iter().filter(|n| f(n)).count();
+        (
+            count(|n| n.y.abs() < 1e-6),
+            count(|n| n.y < -1e-6),
+            count(|n| n.y > 1e-6),
+        )
+    }
+
+    fn xyz(p: &Point3D) -> (f32, f32, f32) {
+        (p.x, p.y, p.z)
+    }
+
+    fn center(vertices: &[Point3D]) -> Point3D {
+        &vertices.iter().fold(Point3D::ZERO, |sum, v| &sum + v) * (1. / vertices.len() as f32)
+    }
+
+    // Every face looks into the room: a step along its normal stays inside
+    // the sector, between its floor and ceiling
+    fn assert_faces_inwards(map: &WadMap) {
+        let mesh = map.to_mesh();
+        for (face, normal) in mesh.faces.iter().zip(mesh.face_normals()) {
+            let inside = &center(&mesh.face_vertices(face)) + &normal;
+            assert_eq!(map.sector_at(inside.x, inside.z), Some(0));
+            assert!(inside.y < 0. && inside.y > -128.);
+        }
+    }
+
+    #[test]
+    fn lump_directory() {
+        let wad = Wad::parse(&wad(&square_room())).unwrap();
+
+        assert!(!wad.iwad);
+        let names: Vec<&str> = wad.lumps.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["MAP01", "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SECTORS"]
+        );
+        assert_eq!(
+            wad.lump("VERTEXES").unwrap().data,
+            vertexes(&[(0, 0), (0, 128), (128, 128), (128, 0)])
+        );
+        assert_eq!(wad.lump("SECTORS").unwrap().data.len(), 26);
+        assert_eq!(wad.map_names(), ["MAP01"]);
+    }
+
+    #[test]
+    fn broken_files() {
+        let data = wad(&square_room());
+        assert!(Wad::parse(&data[..8]).is_err());
+        assert!(Wad::parse(b"JUNK\0\0\0\0\0\0\0\0").is_err());
+        // The directory is at the end
+        assert!(Wad::parse(&data[..data.len() - 1]).is_err());
+
+        let mut lump_past_end = data.clone();
+        let directory = read_i32(&data, 8) as usize;
+        lump_past_end[directory + 4..directory + 8].copy_from_slice(&1000i32.to_le_bytes());
+        assert!(Wad::parse(&lump_past_end).is_err());
+    }
+
+    #[test]
+    fn map_validation() {
+        let message = format_error(load(&replace(square_room(), "LINEDEFS", vec![0; 15])));
+        assert!(message.contains("14 byte records"), "{}", message);
+
+        let message = format_error(load(&replace(square_room(), "SECTORS", vec![0; 20])));
+        assert!(message.contains("26 byte records"), "{}", message);
+
+        let lines = linedefs(&[(0, 9, 0, NONE)]);
+        let message = format_error(load(&replace(square_room(), "LINEDEFS", lines)));
+        assert!(message.contains("no vertex 9"), "{}", message);
+
+        let lines = linedefs(&[(0, 1, 0, 4)]);
+        let message = format_error(load(&replace(square_room(), "LINEDEFS", lines)));
+        assert!(message.contains("no sidedef 4"), "{}", message);
+
+        let sides = sidedefs(&[0, 0, 0, 2]);
+        let message = format_error(load(&replace(square_room(), "SIDEDEFS", sides)));
+        assert!(message.contains("sidedef 3: no sector 2"), "{}", message);
+
+        let mut lumps = square_room();
+        lumps.retain(|(n, _)| *n != "SECTORS");
+        let message = format_error(load(&lumps));
+        assert!(message.contains("no SECTORS lump"), "{}", message);
+
+        assert!(Wad::parse(&wad(&square_room()))
+            .unwrap()
+            .map("MAP02")
+            .is_err());
+    }
+
+    #[test]
+    fn square_room_mesh() {
+        let map = load(&square_room()).unwrap();
+        assert_eq!(map.vertices.len(), 4);
+        assert_eq!(map.linedefs.len(), 4);
+
+        let mesh = map.to_mesh();
+        assert_eq!(count_faces(&mesh), (4, 2, 2));
+        assert_faces_inwards(&map);
+    }
+
+    #[test]
+    fn room_with_pillar_mesh() {
+        let map = load(&room_with_pillar()).unwrap();
+        let mesh = map.to_mesh();
+
+        // A square with a square hole, bridged, is 8 triangles
+        assert_eq!(count_faces(&mesh), (8, 8, 8));
+        assert_faces_inwards(&map);
+
+        // Faces sharing no area: the floor covers the room but the pillar
+        let floor: f32 = mesh
+            .faces
+            .iter()
+            .map(|f| mesh.face_normal(f))
+            .filter(|n| n.y < 0.)
+            .map(|n| n.norm() / 2.)
+            .sum();
+        assert!((floor - (256. * 256. - 64. * 64.)).abs() < 1e-2);
+        assert_eq!(map.sector_at(128., 128.), None);
+    }
+
+    #[test]
+    fn player_start() {
+        let map = load(&square_room()).unwrap();
+        let (position, yaw) = map.player_start().unwrap();
+        assert_eq!(xyz(&position), (64., -EYE_HEIGHT, 32.));
+        // Angle 90 is north, where yaw 0 looks
+        assert!(yaw.abs() < 1e-6);
+
+        // East is a quarter turn right, and the eye is above the floor
+        let lumps = replace(square_room(), "THINGS", things(32, 100, 0));
+        let lumps = replace(lumps, "SECTORS", sectors(&[(24, 128)]));
+        let map = load(&lumps).unwrap();
+        let (position, yaw) = map.player_start().unwrap();
+        assert_eq!(xyz(&position), (32., -(24. + EYE_HEIGHT), 100.));
+        assert!((yaw - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+
+        let level = map.level();
+        assert_eq!(xyz(&level.spawn), xyz(&position));
+        assert_eq!(level.spawn_yaw, yaw);
+        assert_eq!(level.objects.len(), 1);
+        assert_eq!(level.objects[0].mesh.faces.len(), map.to_mesh().faces.len());
+    }
+}